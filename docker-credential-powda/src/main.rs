@@ -0,0 +1,171 @@
+//! docker's credential helper protocol, so `"credsStore": "powda"` in
+//! `~/.docker/config.json` keeps registry logins in the vault.
+//!
+//! docker runs `docker-credential-powda <operation>` and talks over
+//! stdin/stdout: `get` and `erase` read a server URL, `store` reads
+//! `{"ServerURL", "Username", "Secret"}`, `list` prints a URL to username
+//! map. Errors go to stdout too, with a non-zero exit code. The vault is
+//! the one `powda` would use: `$POWDA_VAULT`, else the `vault` setting in
+//! config.toml, else the default vault.
+
+mod registry;
+
+use powda_core::agent::{self, AgentClient};
+use powda_core::{EntryMetadata, EntryQuery, Error, Password, PasswordEntry, Result, Store, StoreRepository, VaultRegistry};
+use registry::Registry;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+/// docker matches this text to tell "no credentials" from a failure.
+const NOT_FOUND: &str = "credentials not found in native keychain";
+
+#[derive(Serialize, Deserialize)]
+struct Credentials {
+    #[serde(rename = "ServerURL", default)]
+    server_url: String,
+    #[serde(rename = "Username")]
+    username: String,
+    #[serde(rename = "Secret")]
+    secret: String,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let operation = std::env::args().nth(1).unwrap_or_default();
+    if let Err(e) = run(&operation).await {
+        let message = match e {
+            Error::NotFound(_) => NOT_FOUND.to_string(),
+            e => e.to_string(),
+        };
+        println!("{}", message);
+        std::process::exit(1);
+    }
+}
+
+async fn run(operation: &str) -> Result<()> {
+    match operation {
+        "get" => get(&*open().await?, &Registry::parse(&read_stdin()?)?).await,
+        "store" => {
+            let credentials: Credentials = serde_json::from_str(&read_stdin()?)?;
+            store(&*open().await?, credentials).await
+        }
+        "erase" => erase(&*open().await?, &Registry::parse(&read_stdin()?)?).await,
+        "list" => list(&*open().await?).await,
+        "version" => {
+            println!("docker-credential-powda {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+        _ => Err(Error::InvalidInput(format!(
+            "Unknown operation '{}'; expected get, store, erase, list or version", operation
+        ))),
+    }
+}
+
+/// The user's vault, through the agent when one is running. Without an
+/// unlocked agent the master password is asked for on the terminal.
+async fn open() -> Result<Box<dyn StoreRepository>> {
+    let vault = VaultRegistry::load()?.resolve_configured()?;
+    let store: Box<dyn StoreRepository> = match AgentClient::connect(agent::socket_path(&vault.path)?) {
+        Some(client) => Box::new(client),
+        None => Box::new(Store::with_path(vault.path.clone())),
+    };
+    if !store.exists().await {
+        return Err(Error::NotInitialized);
+    }
+    if store.is_locked().await {
+        let password = rpassword::prompt_password("Enter master password: ")
+            .map_err(|_| Error::Encryption("The vault is locked; run 'powda unlock' first".to_string()))?;
+        store.unlock(&password).await.map_err(|e| match e {
+            Error::HeaderTampered => e,
+            _ => Error::Encryption("Invalid Master password".to_string()),
+        })?;
+    }
+    Ok(store)
+}
+
+fn read_stdin() -> Result<String> {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+fn write_json(value: &impl Serialize) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    serde_json::to_writer(&mut stdout, value)?;
+    writeln!(stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The entry whose URL fits `registry` best, looking through the whole
+/// vault so logins saved by hand are found too.
+async fn best_match(store: &dyn StoreRepository, registry: &Registry) -> Result<Option<PasswordEntry>> {
+    let found = store.query(&EntryQuery::default()).await?.into_iter()
+        .filter_map(|entry| Some((registry.score(entry.url.as_deref()?)?, entry)))
+        .rev()
+        .max_by_key(|(score, _)| *score);
+    match found {
+        Some((_, summary)) => Ok(Some(store.get(&summary.name).await?)),
+        None => Ok(None),
+    }
+}
+
+async fn get(store: &dyn StoreRepository, registry: &Registry) -> Result<()> {
+    let entry = best_match(store, registry).await?
+        .ok_or_else(|| Error::NotFound(registry.host.clone()))?;
+    write_json(&Credentials {
+        server_url: entry.metadata.url.clone().unwrap_or_default(),
+        username: entry.metadata.username.clone().unwrap_or_default(),
+        secret: entry.password.as_str().to_string(),
+    })
+}
+
+async fn store(store: &dyn StoreRepository, credentials: Credentials) -> Result<()> {
+    let registry = Registry::parse(&credentials.server_url)?;
+    let name = registry.entry_name()?;
+    let password = Password::new(credentials.secret).map_err(Error::InvalidInput)?;
+    let metadata = EntryMetadata {
+        url: Some(credentials.server_url),
+        username: Some(credentials.username).filter(|u| !u.is_empty()),
+        ..Default::default()
+    };
+
+    match store.get(&name).await {
+        Ok(mut entry) => {
+            if entry.password.as_str() != password.as_str() {
+                entry.update_password(password);
+            }
+            entry.metadata.url = metadata.url;
+            entry.metadata.username = metadata.username;
+            store.update(entry).await
+        }
+        Err(Error::NotFound(_)) => store.add(PasswordEntry::new(name, password).with_metadata(metadata)).await,
+        Err(e) => Err(e),
+    }
+}
+
+/// Remove what `store` saved for `registry`; entries outside the docker
+/// folder are the user's own and are left alone.
+async fn erase(store: &dyn StoreRepository, registry: &Registry) -> Result<()> {
+    let mut erased = false;
+    for entry in store.query(&EntryQuery::default()).await? {
+        let same = entry.url.as_deref().is_some_and(|url| Registry::parse(url).is_ok_and(|r| r == *registry));
+        if same && entry.name.is_under(registry::FOLDER) {
+            store.remove(&entry.name).await?;
+            erased = true;
+        }
+    }
+    match erased {
+        true => Ok(()),
+        false => Err(Error::NotFound(registry.host.clone())),
+    }
+}
+
+async fn list(store: &dyn StoreRepository) -> Result<()> {
+    let logins: BTreeMap<String, String> = store.query(&EntryQuery::default()).await?.into_iter()
+        .filter(|entry| entry.name.is_under(registry::FOLDER))
+        .filter_map(|entry| Some((entry.url?, entry.username.unwrap_or_default())))
+        .collect();
+    write_json(&logins)
+}
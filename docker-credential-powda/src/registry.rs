@@ -0,0 +1,69 @@
+use powda_core::{EntryName, Error, Result};
+
+/// Folder holding the credentials docker stores; `list` and `erase` only
+/// look here, `get` also finds entries created by hand elsewhere.
+pub const FOLDER: &str = "docker";
+
+/// A registry address reduced to lowercase `host[:port]` and path, so
+/// `https://index.docker.io/v1/` and `index.docker.io/v1` are the same.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Registry {
+    pub host: String,
+    pub path: String,
+}
+
+impl Registry {
+    pub fn parse(server: &str) -> Result<Self> {
+        let rest = server.trim().split_once("://").map_or(server.trim(), |(_, rest)| rest);
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let host = host.rsplit('@').next().unwrap_or(host).to_ascii_lowercase();
+        if host.is_empty() {
+            return Err(Error::InvalidInput(format!("'{}' is not a registry address", server)));
+        }
+        Ok(Self { host, path: path.trim_matches('/').to_string() })
+    }
+
+    /// How well an entry with `url` fits: `None` for another registry,
+    /// higher for an exact path than for a bare host.
+    pub fn score(&self, url: &str) -> Option<usize> {
+        let entry = Registry::parse(url).ok()?;
+        if entry.host != self.host {
+            return None;
+        }
+        match (entry.path.is_empty(), entry.path == self.path) {
+            (_, true) => Some(2),
+            (true, false) => Some(1),
+            (false, false) => None,
+        }
+    }
+
+    /// Where `store` keeps this registry's credentials.
+    pub fn entry_name(&self) -> Result<EntryName> {
+        let name = match self.path.is_empty() {
+            true => format!("{}/{}", FOLDER, self.host),
+            false => format!("{}/{}/{}", FOLDER, self.host, self.path),
+        };
+        EntryName::new(name).map_err(Error::InvalidInput)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_addresses_are_normalised_and_matched() {
+        let hub = Registry::parse("https://index.docker.io/v1/").unwrap();
+        assert_eq!(hub, Registry { host: "index.docker.io".to_string(), path: "v1".to_string() });
+        assert_eq!(hub.entry_name().unwrap().as_str(), "docker/index.docker.io/v1");
+        assert_eq!(hub.score("index.docker.io/v1"), Some(2));
+        assert_eq!(hub.score("https://Index.Docker.io"), Some(1));
+        assert_eq!(hub.score("https://index.docker.io/v2/"), None);
+
+        let local = Registry::parse("localhost:5000").unwrap();
+        assert_eq!(local.entry_name().unwrap().as_str(), "docker/localhost:5000");
+        assert_eq!(local.score("http://localhost:5000"), Some(2));
+        assert_eq!(local.score("http://localhost:5001"), None);
+        assert!(Registry::parse("https://").is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use powda_core::agent::{self, AgentClient, RemoteError, Request, Response};
+use powda_core::{Error, Result, SessionPolicy, Store, StoreRepository, VaultLocation};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+/// How often the agent checks whether the session has expired.
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Build a policy from CLI seconds, where 0 means "no limit".
+pub fn session_policy(idle_timeout: u64, max_lifetime: u64) -> SessionPolicy {
+    let limit = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+    SessionPolicy::new(limit(idle_timeout), limit(max_lifetime))
+}
+
+/// Serve `vault` over its agent socket until asked to stop.
+pub async fn serve(vault: &VaultLocation, policy: SessionPolicy) -> Result<()> {
+    let socket = agent::socket_path(&vault.path)?;
+
+    if AgentClient::connect(socket.clone()).is_some() {
+        return Err(Error::Agent(format!("An agent is already listening on {:?}", socket)));
+    }
+    // Nobody answered, so whatever is left is a stale socket from a crash.
+    if socket.exists() {
+        std::fs::remove_file(&socket)?;
+    }
+
+    let listener = UnixListener::bind(&socket)?;
+    std::fs::set_permissions(&socket, std::fs::Permissions::from_mode(0o600))?;
+
+    let store = Arc::new(Store::with_path(vault.path.clone()).with_session_policy(policy));
+    let (stop_tx, mut stop_rx) = watch::channel(false);
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut expiry = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                if !same_user(&stream) {
+                    continue;
+                }
+                let store = store.clone();
+                let stop_tx = stop_tx.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, store, stop_tx).await {
+                        eprintln!("agent: connection error: {}", e);
+                    }
+                });
+            }
+            _ = expiry.tick() => {
+                store.lock_if_expired();
+            }
+            _ = stop_rx.changed() => break,
+            _ = sigterm.recv() => break,
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    store.lock().await?;
+    std::fs::remove_file(&socket).ok();
+    Ok(())
+}
+
+pub(crate) fn same_user(stream: &UnixStream) -> bool {
+    let uid = unsafe { libc::getuid() };
+    stream.peer_cred().map(|cred| cred.uid() == uid).unwrap_or(false)
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    store: Arc<Store>,
+    stop_tx: watch::Sender<bool>,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        // A request this agent cannot read, say from a newer powda, gets an
+        // error reply and the connection stays open.
+        let request: Option<Request> = serde_json::from_str(&line).ok();
+        let shutdown = matches!(request, Some(Request::Shutdown));
+
+        let response: Response = match request {
+            Some(request) => agent::handle_request(store.as_ref(), request).await,
+            None => Response::Error(RemoteError::Agent(
+                "the running agent does not understand this request; restart it with 'powda agent --stop'".to_string(),
+            )),
+        };
+        let mut reply = serde_json::to_vec(&response)?;
+        reply.push(b'\n');
+        writer.write_all(&reply).await?;
+
+        if shutdown {
+            stop_tx.send(true).ok();
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Start `powda agent` for `vault` in the background and wait for its
+/// socket to answer.
+pub fn spawn(vault: &VaultLocation) -> Result<AgentClient> {
+    let socket = agent::socket_path(&vault.path)?;
+    let exe = std::env::current_exe()?;
+
+    let mut command = Command::new(exe);
+    command
+        .arg("--vault")
+        .arg(std::path::absolute(&vault.path)?)
+        .arg("agent")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // Start a new session so the agent outlives the terminal that launched it.
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    command.spawn()?;
+
+    wait_for(&socket)
+        .ok_or_else(|| Error::Agent("Agent did not start in time".to_string()))
+}
+
+fn wait_for(socket: &Path) -> Option<AgentClient> {
+    for _ in 0..50 {
+        if let Some(client) = AgentClient::connect(socket.to_path_buf()) {
+            return Some(client);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    None
+}
+
+/// Client for the agent serving `vault`, if there is one.
+pub fn running(vault: &VaultLocation) -> Option<AgentClient> {
+    AgentClient::connect(agent::socket_path(&vault.path).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use powda_core::{EntryName, KdfParams, Password, PasswordEntry};
+
+    fn temp_vault(name: &str) -> VaultLocation {
+        let dir = std::env::temp_dir().join(format!("powda-agent-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        VaultLocation { name: None, path: dir.join("vault") }
+    }
+
+    // The client blocks on its socket, so the agent needs a thread of its own.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn agent_serves_a_session_over_its_socket() {
+        let vault = temp_vault("session");
+        Store::with_path(vault.path.clone())
+            .init("master password", KdfParams::new(8, 1, 1).unwrap()).await.unwrap();
+        let server = tokio::spawn({
+            let vault = vault.clone();
+            async move { serve(&vault, session_policy(2, 0)).await }
+        });
+        let socket = agent::socket_path(&vault.path).unwrap();
+        let client = tokio::task::spawn_blocking(move || wait_for(&socket)).await.unwrap().unwrap();
+        assert!(serve(&vault, session_policy(0, 0)).await.is_err(), "a second agent must not start");
+
+        assert!(client.is_locked().await);
+        let wrong = client.unlock("wrong password").await.unwrap_err();
+        assert_eq!(wrong.code(), "encryption");
+        client.unlock("master password").await.unwrap();
+
+        let name = EntryName::new("web/github".to_string()).unwrap();
+        client.add(PasswordEntry::new(name.clone(), Password::new("hunter2".to_string()).unwrap())).await.unwrap();
+        assert_eq!(client.get(&name).await.unwrap().password.as_str(), "hunter2");
+        let missing = EntryName::new("web/gitlab".to_string()).unwrap();
+        assert!(matches!(client.get(&missing).await, Err(Error::NotFound(n)) if n == "web/gitlab"));
+        let duplicate = PasswordEntry::new(name.clone(), Password::new("x".to_string()).unwrap());
+        assert_eq!(client.add(duplicate).await.unwrap_err().code(), "already_exists");
+
+        client.lock().await.unwrap();
+        assert!(client.is_locked().await);
+        assert_eq!(client.get(&name).await.unwrap_err().code(), "encryption");
+
+        // An idle session is locked by the agent itself.
+        client.unlock("master password").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(3500)).await;
+        assert!(client.is_locked().await);
+
+        // Requests from a newer or broken client get an error, not a hang-up.
+        let socket = agent::socket_path(&vault.path).unwrap();
+        let replies = tokio::task::spawn_blocking(move || {
+            use std::io::{BufRead, Write};
+            let mut stream = std::os::unix::net::UnixStream::connect(socket).unwrap();
+            stream.write_all(b"{\"op\":\"from_the_future\"}\nnot json\n{\"op\":\"is_locked\"}\n").unwrap();
+            std::io::BufReader::new(stream).lines().take(3).map(|line| line.unwrap()).collect::<Vec<_>>()
+        }).await.unwrap();
+        for reply in &replies[..2] {
+            let response: Response = serde_json::from_str(reply).unwrap();
+            assert!(matches!(response, Response::Error(RemoteError::Agent(_))), "{}", reply);
+        }
+        assert!(matches!(serde_json::from_str(&replies[2]).unwrap(), Response::Bool(true)));
+
+        client.shutdown().unwrap();
+        server.await.unwrap().unwrap();
+        assert!(running(&vault).is_none());
+        std::fs::remove_dir_all(vault.path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn peers_of_the_same_user_are_accepted() {
+        let (ours, _) = UnixStream::pair().unwrap();
+        assert!(same_user(&ours));
+    }
+}
@@ -0,0 +1,147 @@
+use powda_core::{Error, Result};
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+pub const DEFAULT_CLEAR_AFTER: Duration = Duration::from_secs(45);
+
+/// The selection tool for whichever display server this session runs under.
+#[derive(Clone, Copy)]
+enum Backend {
+    Wayland,
+    X11,
+}
+
+impl Backend {
+    fn detect() -> Result<Self> {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            Ok(Backend::Wayland)
+        } else if std::env::var_os("DISPLAY").is_some() {
+            Ok(Backend::X11)
+        } else {
+            Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "No clipboard available (needs a Wayland or X11 session)",
+            )))
+        }
+    }
+
+    fn set_command(self) -> Command {
+        match self {
+            Backend::Wayland => Command::new("wl-copy"),
+            Backend::X11 => {
+                let mut command = Command::new("xclip");
+                command.args(["-selection", "clipboard", "-in"]);
+                command
+            }
+        }
+    }
+
+    fn get_command(self) -> Command {
+        match self {
+            Backend::Wayland => {
+                let mut command = Command::new("wl-paste");
+                command.arg("--no-newline");
+                command
+            }
+            Backend::X11 => {
+                let mut command = Command::new("xclip");
+                command.args(["-selection", "clipboard", "-out"]);
+                command
+            }
+        }
+    }
+
+    fn set(self, text: &str) -> Result<()> {
+        // Both tools fork a child that keeps serving the selection; stdout
+        // must not be a pipe we wait on or we'd block until it is replaced.
+        let mut child = self.set_command()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| tool_error(self, e))?;
+        child.stdin.take().expect("piped stdin").write_all(text.as_bytes())?;
+
+        if !child.wait()?.success() {
+            return Err(Error::Io(std::io::Error::other("Clipboard tool failed")));
+        }
+        Ok(())
+    }
+
+    fn get(self) -> Option<Zeroizing<String>> {
+        let output = self.get_command().stderr(Stdio::null()).output().ok()?;
+        String::from_utf8(output.stdout).ok().map(Zeroizing::new)
+    }
+
+    fn clear(self) -> Result<()> {
+        match self {
+            Backend::Wayland => {
+                Command::new("wl-copy").arg("--clear").status()?;
+                Ok(())
+            }
+            Backend::X11 => self.set(""),
+        }
+    }
+}
+
+fn tool_error(backend: Backend, e: std::io::Error) -> Error {
+    let tool = match backend {
+        Backend::Wayland => "wl-copy",
+        Backend::X11 => "xclip",
+    };
+    Error::Io(std::io::Error::new(e.kind(), format!("Could not run {}: {}", tool, e)))
+}
+
+/// Put `secret` on the clipboard and, if `clear_after` is set, start a
+/// detached helper that clears it once the timeout passes.
+pub fn copy(secret: &str, clear_after: Option<Duration>) -> Result<()> {
+    let backend = Backend::detect()?;
+    backend.set(secret)?;
+
+    if let Some(after) = clear_after {
+        spawn_clearer(secret, after)?;
+    }
+    Ok(())
+}
+
+fn spawn_clearer(secret: &str, after: Duration) -> Result<()> {
+    let exe = std::env::current_exe()?;
+
+    let mut command = Command::new(exe);
+    command
+        .args(["clipboard-clear", "--after", &after.as_secs().to_string()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // Own session, so closing the terminal doesn't take the helper with it.
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+
+    // The secret goes over stdin; argv is visible to every user.
+    let mut child = command.spawn()?;
+    child.stdin.take().expect("piped stdin").write_all(secret.as_bytes())?;
+    Ok(())
+}
+
+/// Body of the detached helper: wait, then clear the clipboard unless the
+/// user has copied something else in the meantime.
+pub fn clear_after(after: Duration) -> Result<()> {
+    let mut secret = Zeroizing::new(String::new());
+    std::io::stdin().read_to_string(&mut secret)?;
+
+    let backend = Backend::detect()?;
+    std::thread::sleep(after);
+
+    if backend.get().is_some_and(|current| *current == *secret) {
+        backend.clear()?;
+    }
+    Ok(())
+}
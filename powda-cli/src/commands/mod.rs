@@ -1,9 +1,22 @@
-use clap::{Parser, Subcommand};
+use crate::config::GeneratorConfig;
+use crate::output::OutputFormat;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use powda_core::generator::{CharsetPolicy, PassphrasePolicy, PronounceablePolicy};
+use powda_core::{timestamp, CollisionStrategy, EntryMetadata, EntryQuery, ExportFormat, KdfParams, Recipe, Result};
+use std::path::PathBuf;
+use std::time::SystemTime;
 
 #[derive(Parser)]
 #[command(name = "powda")]
 #[command(about = "A secure password manager", long_about = None)]
 pub struct Cli {
+    /// Output format for results and errors [default: human]
+    #[arg(long, global = true, value_enum)]
+    pub format: Option<OutputFormat>,
+    /// Vault to use, by registered name or path [env: POWDA_VAULT]
+    #[arg(long, global = true)]
+    pub vault: Option<String>,
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -13,19 +26,603 @@ pub enum Commands {
     Init{
         #[arg(short, long)]
         force: bool,
+        #[command(flatten)]
+        kdf: KdfArgs,
     },
     
     Unlock,
     Lock,
     Add {
         name: String,
+        /// Generate the password instead of prompting for it
+        #[arg(short, long)]
+        generate: bool,
+        #[command(flatten)]
+        generator: GeneratorArgs,
+        #[command(flatten)]
+        metadata: MetadataArgs,
+        /// otpauth:// URI or base32 TOTP secret for one-time codes
+        #[arg(long, value_name = "URI")]
+        otp: Option<String>,
+    },
+    /// Change an entry's password or metadata
+    Edit {
+        name: String,
+        /// Prompt for a new password
+        #[arg(long, conflicts_with = "generate")]
+        password: bool,
+        /// Replace the password with a generated one
+        #[arg(short, long)]
+        generate: bool,
+        #[command(flatten)]
+        generator: GeneratorArgs,
+        #[command(flatten)]
+        metadata: MetadataArgs,
+        /// Remove a tag (repeatable)
+        #[arg(long = "untag", value_name = "TAG")]
+        untag: Vec<String>,
+        /// otpauth:// URI or base32 TOTP secret; empty removes it
+        #[arg(long, value_name = "URI")]
+        otp: Option<String>,
+    },
+    /// List the previous passwords of an entry, newest first
+    History {
+        name: String,
+        /// Print the passwords instead of masking them
+        #[arg(long)]
+        reveal: bool,
+    },
+    /// Make a previous password current again
+    Restore {
+        name: String,
+        /// History version to restore, as numbered by `powda history`
+        #[arg(long)]
+        version: usize,
+    },
+    /// Show or change how much password history entries keep
+    Retention {
+        /// Previous passwords kept per entry
+        #[arg(long)]
+        max_versions: Option<usize>,
+        /// Forget passwords replaced more than this many days ago (0 keeps them)
+        #[arg(long)]
+        max_age_days: Option<u32>,
+    },
+    /// Print the current one-time code of an entry
+    Otp {
+        name: String,
     },
+    /// Show every field of an entry
+    Show {
+        name: String,
+        /// Print the password instead of masking it
+        #[arg(long)]
+        reveal: bool,
+    },
+    /// Copy a password to the clipboard (or print it with --print)
     Get {
         name: String,
+        /// Copy to the clipboard (the default)
+        #[arg(short, long, conflicts_with = "print")]
+        copy: bool,
+        /// Print to stdout instead of copying
+        #[arg(short, long)]
+        print: bool,
+        /// Clear the clipboard after this many seconds (0 keeps it) [default: 45]
+        #[arg(long)]
+        clear_after: Option<u64>,
+    },
+    /// List entry names, optionally only those under a folder
+    List {
+        /// Folder (or name) to list, e.g. `work/aws`
+        prefix: Option<String>,
+        /// Draw folders as a tree
+        #[arg(long)]
+        tree: bool,
     },
-    List,
     Remove {
         name: String,
+        /// Remove without asking; required with --format json
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Run a command with passwords in its environment
+    Run {
+        /// Set VAR to the password of ENTRY (repeatable)
+        #[arg(long = "env", value_name = "VAR=ENTRY", value_parser = parse_env_mapping, required = true)]
+        env: Vec<(String, String)>,
+        /// Give the command our terminal instead of masking the passwords in its output
+        #[arg(long)]
+        no_mask: bool,
+        /// The command and its arguments, after `--`
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+    /// Fill in `{{ powda "NAME" "FIELD" }}` and `powda://NAME/FIELD` references in a file
+    Inject {
+        /// Template to read, or `-` for stdin
+        template: PathBuf,
+        /// Where to write the result (created 0600); stdout if left out
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// git credential helper; set `credential.helper` to "powda git-credential"
+    GitCredential {
+        #[arg(value_enum)]
+        operation: GitOperation,
+    },
+    /// Store SSH private keys and print their public halves
+    Ssh {
+        #[command(subcommand)]
+        command: SshCommand,
+    },
+    /// Offer the vault's SSH keys to ssh while the vault is unlocked
+    SshAgent {
+        /// Socket to listen on [default: next to the agent's socket]
+        #[arg(long)]
+        socket: Option<PathBuf>,
+        /// Ask before every signature, not only for keys added with --confirm
+        #[arg(long)]
+        confirm: bool,
+    },
+    /// Search entries by name, tag, URL host, username or date
+    Find {
+        #[command(flatten)]
+        query: QueryArgs,
+    },
+    /// Rename an entry or move a whole folder; a target ending in `/` moves into that folder
+    Mv {
+        from: String,
+        to: String,
     },
     ChangeMaster,
+    /// Generate passwords without storing them
+    Generate {
+        #[command(flatten)]
+        generator: GeneratorArgs,
+        /// How many to generate
+        #[arg(short = 'n', long, default_value_t = 1)]
+        count: usize,
+    },
+    /// Import entries from another password manager's export
+    Import {
+        file: PathBuf,
+        #[arg(long, value_enum)]
+        from: ImportSource,
+        /// What to do with entries whose name is already taken
+        #[arg(long, value_enum, default_value_t = OnConflict::Skip)]
+        on_conflict: OnConflict,
+    },
+    /// Write every entry to a file, encrypted unless a plaintext format is chosen
+    Export {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportTarget::Archive)]
+        to: ExportTarget,
+        /// Confirm that a plaintext format may write passwords unencrypted
+        #[arg(long)]
+        unencrypted: bool,
+        #[command(flatten)]
+        kdf: KdfArgs,
+    },
+    /// Read and change settings in config.toml
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Manage named vaults
+    Vault {
+        #[command(subcommand)]
+        command: VaultCommand,
+    },
+    /// Inspect or change the key derivation cost
+    Kdf {
+        #[command(subcommand)]
+        command: KdfCommand,
+    },
+    /// List or restore encrypted vault backups
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommand,
+    },
+    /// Helper started by `get`; clears the clipboard after a delay
+    #[command(hide = true)]
+    ClipboardClear {
+        #[arg(long)]
+        after: u64,
+    },
+    /// Browse the vault in a full-screen terminal UI
+    Tui {
+        /// Lock after this many seconds without a key press (0 disables) [default: 900]
+        #[arg(long)]
+        idle_timeout: Option<u64>,
+        /// Lock this many seconds after unlocking, however busy (0 disables) [default: 28800]
+        #[arg(long)]
+        max_lifetime: Option<u64>,
+        /// Settings for passwords generated with Ctrl-G
+        #[command(flatten)]
+        generator: GeneratorArgs,
+    },
+    /// Run the background agent that keeps the vault unlocked
+    Agent {
+        #[arg(long)]
+        stop: bool,
+        /// Lock after this many seconds without use (0 disables) [default: 900]
+        #[arg(long)]
+        idle_timeout: Option<u64>,
+        /// Lock this many seconds after unlocking, however busy (0 disables) [default: 28800]
+        #[arg(long)]
+        max_lifetime: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Show every setting, its effective value and where it came from
+    List,
+    /// Print the effective value of a setting
+    Get {
+        key: String,
+    },
+    /// Store a setting in the config file
+    Set {
+        key: String,
+        value: String,
+    },
+    /// Open the config file in $VISUAL or $EDITOR and check it afterwards
+    Edit,
+}
+
+#[derive(Subcommand)]
+pub enum VaultCommand {
+    /// Show the known vaults and which one is the default
+    List,
+    /// Register a vault and initialize it unless the file already exists
+    Create {
+        name: String,
+        /// Where to keep it; defaults to the data directory
+        #[arg(long)]
+        path: Option<PathBuf>,
+        #[command(flatten)]
+        kdf: KdfArgs,
+    },
+    /// Unregister a vault
+    Remove {
+        name: String,
+        /// Also delete the vault file and its backups
+        #[arg(long)]
+        delete: bool,
+    },
+    /// Show the default vault, or make NAME the default
+    Default {
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SshCommand {
+    /// Store an OpenSSH private key, asking for its passphrase if it has one
+    Add {
+        name: String,
+        /// Key file, or `-` for stdin
+        file: PathBuf,
+        /// Have `powda ssh-agent` ask before each signature with this key
+        #[arg(long)]
+        confirm: bool,
+        /// Replace the key already stored under NAME
+        #[arg(long)]
+        replace: bool,
+    },
+    /// Show the stored keys and their fingerprints
+    List,
+    /// Print a key's public half in authorized_keys format
+    ExportPublic {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum KdfCommand {
+    /// Show the Argon2 parameters of the vault
+    Show,
+    /// Re-derive the vault key with new parameters, keeping the master password
+    Upgrade {
+        #[command(flatten)]
+        kdf: KdfArgs,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BackupCommand {
+    /// Show the available backups, newest first
+    List,
+    /// Replace the vault with backup N
+    Restore {
+        index: usize,
+    },
+}
+
+/// Argon2id cost flags; anything left out keeps its base value.
+#[derive(Args)]
+pub struct KdfArgs {
+    /// Memory cost in KiB
+    #[arg(long = "kdf-memory")]
+    pub memory: Option<u32>,
+    /// Number of passes
+    #[arg(long = "kdf-iterations")]
+    pub iterations: Option<u32>,
+    /// Degree of parallelism
+    #[arg(long = "kdf-parallelism")]
+    pub parallelism: Option<u32>,
+}
+
+impl KdfArgs {
+    pub fn resolve(&self, base: KdfParams) -> Result<KdfParams> {
+        KdfParams::new(
+            self.memory.unwrap_or(base.memory_kib),
+            self.iterations.unwrap_or(base.iterations),
+            self.parallelism.unwrap_or(base.parallelism),
+        )
+    }
+}
+
+/// Entry metadata flags. On `edit` an empty value clears the field and
+/// `--tag` adds to the existing tags.
+#[derive(Args)]
+pub struct MetadataArgs {
+    #[arg(long)]
+    pub username: Option<String>,
+    #[arg(long)]
+    pub url: Option<String>,
+    #[arg(long)]
+    pub notes: Option<String>,
+    /// Tag the entry (repeatable)
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+}
+
+impl MetadataArgs {
+    pub fn is_empty(&self) -> bool {
+        self.username.is_none() && self.url.is_none() && self.notes.is_none() && self.tags.is_empty()
+    }
+
+    pub fn apply(&self, metadata: &mut EntryMetadata) {
+        let set = |field: &mut Option<String>, value: &Option<String>| {
+            if let Some(value) = value {
+                *field = (!value.is_empty()).then(|| value.clone());
+            }
+        };
+        set(&mut metadata.username, &self.username);
+        set(&mut metadata.url, &self.url);
+        set(&mut metadata.notes, &self.notes);
+
+        for tag in &self.tags {
+            if !metadata.tags.contains(tag) {
+                metadata.tags.push(tag.clone());
+            }
+        }
+    }
+}
+
+/// Filters for `find`; every one given must match. Dates are
+/// `YYYY-MM-DD` (midnight UTC) or RFC 3339.
+#[derive(Args)]
+pub struct QueryArgs {
+    /// Name glob: `*` and `?` stay within a folder, `**` crosses folders;
+    /// without a `/` it matches the last part of the name
+    pub pattern: Option<String>,
+    /// Only entries with this tag (repeatable, all must match)
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Only entries whose URL is on this host or a subdomain of it
+    #[arg(long)]
+    pub host: Option<String>,
+    /// Only entries whose username contains this
+    #[arg(long)]
+    pub username: Option<String>,
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub created_after: Option<SystemTime>,
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub created_before: Option<SystemTime>,
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub modified_after: Option<SystemTime>,
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub modified_before: Option<SystemTime>,
+}
+
+impl QueryArgs {
+    pub fn query(self) -> EntryQuery {
+        EntryQuery {
+            name: self.pattern,
+            tags: self.tags,
+            host: self.host,
+            username: self.username,
+            created_after: self.created_after,
+            created_before: self.created_before,
+            modified_after: self.modified_after,
+            modified_before: self.modified_before,
+        }
+    }
+}
+
+fn parse_env_mapping(value: &str) -> std::result::Result<(String, String), String> {
+    let (var, entry) = value.split_once('=').ok_or_else(|| format!("'{}' is not VAR=ENTRY", value))?;
+    let valid = var.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid || entry.is_empty() {
+        return Err(format!("'{}' is not VAR=ENTRY", value));
+    }
+    Ok((var.to_string(), entry.to_string()))
+}
+
+fn parse_date(value: &str) -> std::result::Result<SystemTime, String> {
+    timestamp::parse_date(value).ok_or_else(|| format!("'{}' is not a YYYY-MM-DD date or RFC 3339 timestamp", value))
+}
+
+/// The operations of git's credential helper protocol.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum GitOperation {
+    /// Look up credentials for the URL git sends
+    Get,
+    /// Save credentials git reports as working (needs git.store_folder)
+    Store,
+    /// Forget credentials git reports as rejected (only in git.store_folder)
+    Erase,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ImportSource {
+    /// Archive written by `powda export` (prompts for its password)
+    Archive,
+    /// KeePass or KeePassXC database (prompts for its password)
+    Kdbx,
+    /// Unencrypted Bitwarden JSON export
+    BitwardenJson,
+    /// 1Password export archive
+    #[value(name = "1pux")]
+    OnePux,
+    /// Chrome or Chromium password CSV
+    ChromeCsv,
+    /// Firefox password CSV
+    FirefoxCsv,
+    /// Any CSV with name, username, password, url, notes, tags, otp or folder headers
+    GenericCsv,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportTarget {
+    /// Password-protected archive for `powda import --from archive`
+    Archive,
+    /// Plaintext JSON with metadata, timestamps and history
+    Json,
+    /// Plaintext CSV readable by `--from generic-csv`; history is not included
+    Csv,
+    /// Plaintext KeePass 2.x XML for KeePass and KeePassXC
+    KeepassXml,
+}
+
+impl ExportTarget {
+    /// `None` for the encrypted archive.
+    pub fn plaintext_format(self) -> Option<ExportFormat> {
+        match self {
+            ExportTarget::Archive => None,
+            ExportTarget::Json => Some(ExportFormat::Json),
+            ExportTarget::Csv => Some(ExportFormat::Csv),
+            ExportTarget::KeepassXml => Some(ExportFormat::KeepassXml),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OnConflict {
+    /// Keep the stored entry
+    Skip,
+    /// Replace it; its old password goes into history
+    Overwrite,
+    /// Import under "name (2)", "name (3)", ...
+    Rename,
+}
+
+impl OnConflict {
+    pub fn strategy(self) -> CollisionStrategy {
+        match self {
+            OnConflict::Skip => CollisionStrategy::Skip,
+            OnConflict::Overwrite => CollisionStrategy::Overwrite,
+            OnConflict::Rename => CollisionStrategy::Rename,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeneratorStyle {
+    /// Random characters from the enabled classes
+    #[default]
+    Chars,
+    /// Alternating consonants and vowels
+    Pronounceable,
+    /// Words from the EFF diceware list
+    Passphrase,
+}
+
+/// Password generator flags; flags that don't apply to the chosen style are ignored.
+#[derive(Args)]
+pub struct GeneratorArgs {
+    /// Password style [default: chars]
+    #[arg(long, value_enum)]
+    pub style: Option<GeneratorStyle>,
+    /// Length in characters
+    #[arg(long)]
+    pub length: Option<usize>,
+    #[arg(long, conflicts_with = "min_lowercase")]
+    pub no_lowercase: bool,
+    #[arg(long, conflicts_with = "min_uppercase")]
+    pub no_uppercase: bool,
+    #[arg(long, conflicts_with = "min_digits")]
+    pub no_digits: bool,
+    #[arg(long, conflicts_with = "min_symbols")]
+    pub no_symbols: bool,
+    #[arg(long)]
+    pub min_lowercase: Option<usize>,
+    #[arg(long)]
+    pub min_uppercase: Option<usize>,
+    /// Minimum digits (for pronounceable passwords: digits appended)
+    #[arg(long)]
+    pub min_digits: Option<usize>,
+    #[arg(long)]
+    pub min_symbols: Option<usize>,
+    /// Leave out look-alike characters such as 0/O and 1/l/I
+    #[arg(long)]
+    pub exclude_ambiguous: bool,
+    /// Number of words in a passphrase
+    #[arg(long)]
+    pub words: Option<usize>,
+    /// Text between passphrase words
+    #[arg(long)]
+    pub separator: Option<String>,
+    /// Capitalise passphrase words or the first pronounceable letter
+    #[arg(long)]
+    pub capitalize: bool,
+}
+
+impl GeneratorArgs {
+    /// Build a recipe from these flags, falling back to `config` for
+    /// anything not given on the command line.
+    pub fn recipe(&self, config: &GeneratorConfig) -> Recipe {
+        let length = self.length.or(config.length);
+        let exclude_ambiguous = self.exclude_ambiguous || config.exclude_ambiguous;
+        let capitalize = self.capitalize || config.capitalize;
+
+        match self.style.unwrap_or(config.style) {
+            GeneratorStyle::Chars => {
+                let base = CharsetPolicy::default();
+                let class = |disabled: bool, min: Option<usize>, base: Option<usize>| {
+                    if disabled { None } else { min.or(base) }
+                };
+                Recipe::Charset(CharsetPolicy {
+                    length: length.unwrap_or(base.length),
+                    lowercase: class(self.no_lowercase, self.min_lowercase, base.lowercase),
+                    uppercase: class(self.no_uppercase, self.min_uppercase, base.uppercase),
+                    digits: class(self.no_digits, self.min_digits, base.digits),
+                    symbols: class(self.no_symbols, self.min_symbols, base.symbols),
+                    exclude_ambiguous,
+                })
+            }
+            GeneratorStyle::Pronounceable => {
+                let base = PronounceablePolicy::default();
+                Recipe::Pronounceable(PronounceablePolicy {
+                    length: length.unwrap_or(base.length),
+                    capitalize,
+                    digits: self.min_digits.unwrap_or(base.digits),
+                })
+            }
+            GeneratorStyle::Passphrase => {
+                let base = PassphrasePolicy::default();
+                Recipe::Passphrase(PassphrasePolicy {
+                    words: self.words.or(config.words).unwrap_or(base.words),
+                    separator: self.separator.clone().or_else(|| config.separator.clone()).unwrap_or(base.separator),
+                    capitalize,
+                })
+            }
+        }
+    }
 }
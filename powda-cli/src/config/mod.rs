@@ -0,0 +1,373 @@
+use crate::clipboard;
+use crate::commands::GeneratorStyle;
+use crate::output::OutputFormat;
+use powda_core::repository::atomic_write;
+use powda_core::vaults::{self, CONFIG_FILE};
+use powda_core::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+/// Effective settings: built-in defaults, overridden by `config.toml`,
+/// overridden by `POWDA_*` environment variables. Command-line flags take
+/// precedence over all of these; the commands apply them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Vault used when `--vault` is not given.
+    pub vault: Option<String>,
+    pub format: OutputFormat,
+    pub clipboard: ClipboardConfig,
+    pub agent: AgentConfig,
+    pub generator: GeneratorConfig,
+    pub git: GitConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    /// Seconds before a copied password is cleared; 0 keeps it.
+    pub clear_after: u64,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self { clear_after: clipboard::DEFAULT_CLEAR_AFTER.as_secs() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgentConfig {
+    /// Seconds without use before the agent locks; 0 disables.
+    pub idle_timeout: u64,
+    /// Seconds after unlocking before the agent locks; 0 disables.
+    pub max_lifetime: u64,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self { idle_timeout: 900, max_lifetime: 8 * 60 * 60 }
+    }
+}
+
+/// Defaults for `generate` and `add --generate`; unset values fall back
+/// to the generator's own defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorConfig {
+    pub style: GeneratorStyle,
+    pub length: Option<usize>,
+    pub words: Option<usize>,
+    pub separator: Option<String>,
+    pub exclude_ambiguous: bool,
+    pub capitalize: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitConfig {
+    /// Folder where `git-credential store` keeps credentials git reports
+    /// as working; unset means git never writes to the vault.
+    pub store_folder: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Text,
+    Number,
+    Flag,
+    Choice(&'static [&'static str]),
+}
+
+/// A setting that can appear in `config.toml`.
+pub struct Key {
+    pub name: &'static str,
+    pub doc: &'static str,
+    kind: Kind,
+}
+
+pub const KEYS: &[Key] = &[
+    Key { name: "vault", doc: "Vault used when --vault is not given", kind: Kind::Text },
+    Key { name: "format", doc: "Output format", kind: Kind::Choice(&["human", "json"]) },
+    Key { name: "clipboard.clear_after", doc: "Seconds before a copied password is cleared (0 keeps it)", kind: Kind::Number },
+    Key { name: "agent.idle_timeout", doc: "Seconds without use before the agent locks (0 disables)", kind: Kind::Number },
+    Key { name: "agent.max_lifetime", doc: "Seconds after unlocking before the agent locks (0 disables)", kind: Kind::Number },
+    Key { name: "generator.style", doc: "Password style", kind: Kind::Choice(&["chars", "pronounceable", "passphrase"]) },
+    Key { name: "generator.length", doc: "Password length in characters", kind: Kind::Number },
+    Key { name: "generator.words", doc: "Words in a passphrase", kind: Kind::Number },
+    Key { name: "generator.separator", doc: "Text between passphrase words", kind: Kind::Text },
+    Key { name: "generator.exclude_ambiguous", doc: "Leave out look-alike characters", kind: Kind::Flag },
+    Key { name: "generator.capitalize", doc: "Capitalise words or the first letter", kind: Kind::Flag },
+    Key { name: "git.store_folder", doc: "Folder for credentials saved by git-credential (unset: never save)", kind: Kind::Text },
+];
+
+impl Key {
+    pub fn find(name: &str) -> Result<&'static Key> {
+        KEYS.iter().find(|key| key.name == name).ok_or_else(|| Error::InvalidInput(format!(
+            "unknown config key '{}'; see 'powda config list'", name
+        )))
+    }
+
+    /// `generator.length` is overridden by `POWDA_GENERATOR_LENGTH`.
+    pub fn env_var(&self) -> String {
+        format!("POWDA_{}", self.name.replace('.', "_").to_ascii_uppercase())
+    }
+
+    /// Turn text from the command line or environment into a value.
+    fn parse(&self, raw: &str) -> std::result::Result<Value, String> {
+        let value = match self.kind {
+            Kind::Text | Kind::Choice(_) => Value::String(raw.to_string()),
+            Kind::Number => raw.trim().parse::<i64>().map(Value::Integer)
+                .map_err(|_| "must be a whole number".to_string())?,
+            Kind::Flag => raw.trim().parse::<bool>().map(Value::Boolean)
+                .map_err(|_| "must be true or false".to_string())?,
+        };
+        self.check(&value)?;
+        Ok(value)
+    }
+
+    fn check(&self, value: &Value) -> std::result::Result<(), String> {
+        match (self.kind, value) {
+            (Kind::Text, Value::String(_)) => Ok(()),
+            (Kind::Number, Value::Integer(n)) if *n >= 0 => Ok(()),
+            (Kind::Number, _) => Err("must be a whole number, 0 or more".to_string()),
+            (Kind::Flag, Value::Boolean(_)) => Ok(()),
+            (Kind::Choice(choices), Value::String(s)) if choices.contains(&s.as_str()) => Ok(()),
+            (Kind::Choice(choices), _) => Err(format!("must be one of {}", choices.join(", "))),
+            (Kind::Text, _) => Err("must be a string".to_string()),
+            (Kind::Flag, _) => Err("must be true or false".to_string()),
+        }
+    }
+}
+
+/// Where an effective value came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Default,
+    File,
+    Env,
+}
+
+impl Source {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Source::Default => "default",
+            Source::File => "file",
+            Source::Env => "env",
+        }
+    }
+}
+
+/// The config file and environment, kept apart so `config list` can say
+/// where each value came from and `config set` only rewrites the file.
+pub struct Layers {
+    path: PathBuf,
+    file: Table,
+    env: Table,
+}
+
+impl Layers {
+    pub fn load() -> Result<Self> {
+        let path = config_path()?;
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => Some(text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        Self::from_sources(path, text.as_deref(), |var| std::env::var(var).ok())
+    }
+
+    /// Layers from the config file's `text` (`None` when there is no file)
+    /// and the `POWDA_*` variables `env` returns.
+    fn from_sources(path: PathBuf, text: Option<&str>, env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let file = match text {
+            Some(text) => parse_file(&path, text)?,
+            None => Table::new(),
+        };
+
+        let mut vars = Table::new();
+        for key in KEYS {
+            let var = key.env_var();
+            if let Some(raw) = env(&var) {
+                let value = key.parse(&raw)
+                    .map_err(|e| Error::InvalidInput(format!("{} ({}) {}", var, key.name, e)))?;
+                insert(&mut vars, key.name, value);
+            }
+        }
+        Ok(Self { path, file, env: vars })
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn config(&self) -> Result<Config> {
+        let mut merged = self.file.clone();
+        for key in KEYS {
+            if let Some(value) = lookup(&self.env, key.name) {
+                insert(&mut merged, key.name, value.clone());
+            }
+        }
+        merged.try_into().map_err(|e: toml::de::Error| Error::InvalidInput(e.message().to_string()))
+    }
+
+    /// Effective value of `key`, or `None` if it is unset everywhere.
+    pub fn get(&self, key: &Key) -> Result<Option<(Value, Source)>> {
+        if let Some(value) = lookup(&self.env, key.name) {
+            return Ok(Some((value.clone(), Source::Env)));
+        }
+        if let Some(value) = lookup(&self.file, key.name) {
+            return Ok(Some((value.clone(), Source::File)));
+        }
+        let defaults = Table::try_from(Config::default())
+            .map_err(|e| Error::InvalidInput(e.to_string()))?;
+        Ok(lookup(&defaults, key.name).map(|value| (value.clone(), Source::Default)))
+    }
+
+    /// Store `raw` for `key` in the config file.
+    pub fn set(&mut self, key: &Key, raw: &str) -> Result<Value> {
+        let value = key.parse(raw)
+            .map_err(|e| Error::InvalidInput(format!("{} {}", key.name, e)))?;
+        insert(&mut self.file, key.name, value.clone());
+        self.save()?;
+        Ok(value)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            vaults::create_private_dir(dir)?;
+        }
+        let text = toml::to_string(&self.file).map_err(|e| Error::InvalidInput(e.to_string()))?;
+        atomic_write(&self.path, text.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        Layers::load()?.config()
+    }
+}
+
+pub fn config_path() -> Result<PathBuf> {
+    Ok(vaults::config_dir()?.join(CONFIG_FILE))
+}
+
+/// Parse and validate `text`, naming the offending key on errors.
+pub fn parse_file(path: &std::path::Path, text: &str) -> Result<Table> {
+    let table: Table = toml::from_str(text)
+        .map_err(|e| Error::InvalidInput(format!("{}: {}", path.display(), e)))?;
+    validate(&table, "")
+        .map_err(|e| Error::InvalidInput(format!("{}: {}", path.display(), e)))?;
+    Ok(table)
+}
+
+fn validate(table: &Table, prefix: &str) -> std::result::Result<(), String> {
+    for (name, value) in table {
+        let name = format!("{}{}", prefix, name);
+        match value {
+            Value::Table(section) if KEYS.iter().any(|key| key.name.starts_with(&format!("{}.", name))) => {
+                validate(section, &format!("{}.", name))?;
+            }
+            _ => {
+                let key = Key::find(&name).map_err(|_| format!("unknown key '{}'", name))?;
+                key.check(value).map_err(|e| format!("'{}' {}", name, e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn lookup<'a>(table: &'a Table, name: &str) -> Option<&'a Value> {
+    match name.split_once('.') {
+        Some((section, rest)) => lookup(table.get(section)?.as_table()?, rest),
+        None => table.get(name),
+    }
+}
+
+fn insert(table: &mut Table, name: &str, value: Value) {
+    match name.split_once('.') {
+        Some((section, rest)) => {
+            let section = table.entry(section).or_insert_with(|| Value::Table(Table::new()));
+            if !section.is_table() {
+                *section = Value::Table(Table::new());
+            }
+            if let Value::Table(section) = section {
+                insert(section, rest, value);
+            }
+        }
+        None => {
+            table.insert(name.to_string(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Cli, Commands};
+    use clap::Parser;
+    use powda_core::Recipe;
+    use std::collections::HashMap;
+
+    fn layers(text: Option<&str>, env: &[(&str, &str)]) -> Result<Layers> {
+        let env: HashMap<String, String> = env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Layers::from_sources(PathBuf::from("config.toml"), text, |var| env.get(var).cloned())
+    }
+
+    fn generated_length(args: &[&str], config: &Config) -> usize {
+        let Commands::Generate { generator, .. } = Cli::try_parse_from(args).unwrap().command else {
+            panic!("not a generate command");
+        };
+        match generator.recipe(&config.generator) {
+            Recipe::Charset(policy) => policy.length,
+            _ => panic!("expected a character recipe"),
+        }
+    }
+
+    const FILE: &str = "vault = \"work\"\n[clipboard]\nclear_after = 20\n[generator]\nlength = 20\n";
+
+    #[test]
+    fn defaults_file_env_and_flags_override_in_that_order() {
+        let defaults = layers(None, &[]).unwrap();
+        assert_eq!(defaults.config().unwrap().clipboard.clear_after, clipboard::DEFAULT_CLEAR_AFTER.as_secs());
+        let key = Key::find("clipboard.clear_after").unwrap();
+        assert_eq!(defaults.get(key).unwrap().unwrap().1, Source::Default);
+
+        let file = layers(Some(FILE), &[]).unwrap();
+        let config = file.config().unwrap();
+        assert_eq!(config.clipboard.clear_after, 20);
+        assert_eq!(config.vault.as_deref(), Some("work"));
+        assert_eq!(file.get(key).unwrap(), Some((Value::Integer(20), Source::File)));
+
+        let env = layers(Some(FILE), &[("POWDA_CLIPBOARD_CLEAR_AFTER", "30"), ("POWDA_GENERATOR_LENGTH", "30")]).unwrap();
+        let config = env.config().unwrap();
+        assert_eq!(config.clipboard.clear_after, 30);
+        assert_eq!(config.vault.as_deref(), Some("work"));
+        assert_eq!(env.get(key).unwrap(), Some((Value::Integer(30), Source::Env)));
+
+        assert_eq!(generated_length(&["powda", "generate"], &config), 30);
+        assert_eq!(generated_length(&["powda", "generate", "--length", "40"], &config), 40);
+        let cli = Cli::try_parse_from(["powda", "--vault", "personal", "list"]).unwrap();
+        assert_eq!(cli.vault.or(config.vault).as_deref(), Some("personal"));
+    }
+
+    #[test]
+    fn bad_values_name_their_key() {
+        let message = |result: Result<Layers>| match result {
+            Err(Error::InvalidInput(message)) => message,
+            other => panic!("expected invalid input, got {:?}", other.map(|_| ())),
+        };
+
+        let file = message(layers(Some("[clipboard]\nclear_after = \"x\"\n"), &[]));
+        assert!(file.contains("'clipboard.clear_after' must be a whole number"), "{}", file);
+
+        let unknown = message(layers(Some("clipboard_timeout = \"x\"\n"), &[]));
+        assert!(unknown.contains("unknown key 'clipboard_timeout'"), "{}", unknown);
+
+        let env = message(layers(None, &[("POWDA_CLIPBOARD_CLEAR_AFTER", "x")]));
+        assert!(env.contains("POWDA_CLIPBOARD_CLEAR_AFTER (clipboard.clear_after)"), "{}", env);
+    }
+}
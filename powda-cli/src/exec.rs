@@ -0,0 +1,158 @@
+use powda_core::error::Error;
+use powda_core::Result;
+use std::os::unix::process::ExitStatusExt;
+use std::process::Stdio;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::signal::unix::{signal, SignalKind};
+use zeroize::{Zeroize, Zeroizing};
+
+/// What masked secrets are replaced with in the child's output.
+const MASK: &[u8] = b"*****";
+
+/// Run `command` with `env` added to its environment and return the exit
+/// code to pass on. With `mask`, the child's stdout and stderr go through
+/// pipes and any of the `env` values in them is replaced by `*****`;
+/// without it the child gets our terminal directly.
+pub async fn run(command: &[String], env: &[(String, Zeroizing<String>)], mask: bool) -> Result<i32> {
+    let (program, args) = command.split_first()
+        .ok_or_else(|| Error::InvalidInput("No command given to run".to_string()))?;
+
+    let mut child = Command::new(program);
+    child.args(args).stdin(Stdio::inherit());
+    for (name, value) in env {
+        child.env(name, value.as_str());
+    }
+    if mask {
+        child.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let mut child = child.spawn().map_err(|e| Error::InvalidInput(format!("Cannot run '{}': {}", program, e)))?;
+
+    // Ctrl-C reaches the child through the process group; stay alive to
+    // pass on the rest of its output and its exit code.
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let copies = match (child.stdout.take(), child.stderr.take()) {
+        (Some(out), Some(err)) => {
+            let secrets: Vec<Vec<u8>> = env.iter().map(|(_, value)| value.as_bytes().to_vec()).collect();
+            vec![
+                tokio::spawn(copy_masked(out, tokio::io::stdout(), Masker::new(secrets.clone()))),
+                tokio::spawn(copy_masked(err, tokio::io::stderr(), Masker::new(secrets))),
+            ]
+        }
+        _ => Vec::new(),
+    };
+
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status?,
+            _ = interrupt.recv() => {}
+        }
+    };
+    for copy in copies {
+        copy.await.map_err(|e| Error::Io(std::io::Error::other(e)))??;
+    }
+
+    Ok(match (status.code(), status.signal()) {
+        (Some(code), _) => code,
+        // The shell convention for "killed by signal N".
+        (None, Some(signal)) => 128 + signal,
+        (None, None) => 1,
+    })
+}
+
+async fn copy_masked(mut from: impl AsyncRead + Unpin, mut to: impl AsyncWrite + Unpin, mut masker: Masker) -> Result<()> {
+    let mut buf = [0u8; 8192];
+    loop {
+        let read = from.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        to.write_all(&masker.feed(&buf[..read])).await?;
+        to.flush().await?;
+    }
+    to.write_all(&masker.finish()).await?;
+    to.flush().await?;
+    Ok(())
+}
+
+/// Replaces secrets in a byte stream that arrives in arbitrary chunks.
+/// Only a tail that could still grow into a secret is held back, so
+/// prompts without a trailing newline still show up immediately.
+pub struct Masker {
+    secrets: Vec<Vec<u8>>,
+    pending: Vec<u8>,
+}
+
+impl Masker {
+    pub fn new(mut secrets: Vec<Vec<u8>>) -> Self {
+        secrets.retain(|secret| !secret.is_empty());
+        // Longest first, so a secret containing another is masked whole.
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        secrets.dedup();
+        Self { secrets, pending: Vec::new() }
+    }
+
+    /// Take the next chunk and return what can safely be written.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(chunk);
+        let masked = self.mask(&self.pending);
+        let hold = self.partial_suffix(&masked);
+        self.pending = masked[masked.len() - hold..].to_vec();
+        masked[..masked.len() - hold].to_vec()
+    }
+
+    /// Everything still held back, at the end of the stream.
+    pub fn finish(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.pending)
+    }
+
+    fn mask(&self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len());
+        let mut i = 0;
+        'scan: while i < data.len() {
+            for secret in &self.secrets {
+                if data[i..].starts_with(secret) {
+                    out.extend_from_slice(MASK);
+                    i += secret.len();
+                    continue 'scan;
+                }
+            }
+            out.push(data[i]);
+            i += 1;
+        }
+        out
+    }
+
+    /// Length of the longest end of `data` that is the start of a secret.
+    fn partial_suffix(&self, data: &[u8]) -> usize {
+        self.secrets.iter()
+            .flat_map(|secret| (1..secret.len().min(data.len() + 1)).rev()
+                .find(|&len| data.ends_with(&secret[..len])))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Drop for Masker {
+    fn drop(&mut self) {
+        self.secrets.zeroize();
+        self.pending.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_split_across_chunks_are_masked() {
+        let mut masker = Masker::new(vec![b"hunter2".to_vec(), b"s3cret".to_vec()]);
+        let mut out = masker.feed(b"user=alice pass=hun");
+        // Only the possible start of a secret is held back.
+        assert_eq!(out, b"user=alice pass=");
+        out.extend(masker.feed(b"ter2 key=s3"));
+        out.extend(masker.feed(b"cretive\n"));
+        out.extend(masker.finish());
+        assert_eq!(String::from_utf8(out).unwrap(), "user=alice pass=***** key=*****ive\n");
+    }
+}
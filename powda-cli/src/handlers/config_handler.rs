@@ -0,0 +1,107 @@
+use powda_core::error::Error;
+use powda_core::Result;
+use crate::config::{self, Key, Layers, KEYS};
+use crate::output::Output;
+use serde_json::json;
+use std::process::Command;
+
+pub struct ConfigHandler {
+    output: Output,
+}
+
+impl ConfigHandler {
+    pub fn new(output: Output) -> Self {
+        Self { output }
+    }
+
+    pub async fn list(&self) -> Result<()> {
+        let layers = Layers::load()?;
+        let mut rows = Vec::new();
+        for key in KEYS {
+            rows.push((key, layers.get(key)?));
+        }
+
+        let value: Vec<_> = rows.iter().map(|(key, current)| json!({
+            "key": key.name,
+            "value": current.as_ref().map(|(value, _)| toml_to_json(value)),
+            "source": current.as_ref().map(|(_, source)| source),
+            "env": key.env_var(),
+            "description": key.doc,
+        })).collect();
+
+        self.output.emit(json!({ "path": layers.path(), "settings": value }), || {
+            println!("Config file: {}", layers.path().display());
+            for (key, current) in &rows {
+                let (value, source) = match current {
+                    Some((value, source)) => (value.to_string(), source.label()),
+                    None => ("(unset)".to_string(), "default"),
+                };
+                println!("{:<28} {:<20} {:<8} {}", key.name, value, source, key.doc);
+            }
+        })
+    }
+
+    pub async fn get(&self, name: String) -> Result<()> {
+        let key = Key::find(&name)?;
+        let current = Layers::load()?.get(key)?;
+
+        let value = json!({
+            "key": key.name,
+            "value": current.as_ref().map(|(value, _)| toml_to_json(value)),
+            "source": current.as_ref().map(|(_, source)| source),
+        });
+        self.output.emit(value, || match &current {
+            Some((toml::Value::String(s), _)) => println!("{}", s),
+            Some((value, _)) => println!("{}", value),
+            None => {}
+        })
+    }
+
+    pub async fn set(&self, name: String, raw: String) -> Result<()> {
+        let key = Key::find(&name)?;
+        let mut layers = Layers::load()?;
+        let value = layers.set(key, &raw)?;
+
+        let shadowed = std::env::var(key.env_var()).is_ok();
+        self.output.emit(json!({ "key": key.name, "value": toml_to_json(&value), "path": layers.path() }), || {
+            println!("✅ Set {} = {}", key.name, value);
+            if shadowed {
+                println!("Note: {} is set and takes precedence over the file.", key.env_var());
+            }
+        })
+    }
+
+    pub async fn edit(&self) -> Result<()> {
+        let path = config::config_path()?;
+        if let Some(dir) = path.parent() {
+            powda_core::vaults::create_private_dir(dir)?;
+        }
+
+        let editor = std::env::var("VISUAL").ok().filter(|e| !e.is_empty())
+            .or_else(|| std::env::var("EDITOR").ok().filter(|e| !e.is_empty()))
+            .unwrap_or_else(|| "vi".to_string());
+        // Let the shell split "code --wait" and the like.
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", editor))
+            .arg("sh")
+            .arg(&path)
+            .status()?;
+        if !status.success() {
+            return Err(Error::InvalidInput(format!("{} exited with {}", editor, status)));
+        }
+
+        match std::fs::read_to_string(&path) {
+            Ok(text) => { config::parse_file(&path, &text)?; }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        self.output.emit(json!({ "path": path, "valid": true }), || {
+            println!("✅ {} is valid", path.display());
+        })
+    }
+}
+
+fn toml_to_json(value: &toml::Value) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or(serde_json::Value::Null)
+}
@@ -1,3 +1,6 @@
-
+mod config_handler;
 mod password_handler;
-pub use password_handler::PasswordHandler;
+mod vault_handler;
+pub use config_handler::ConfigHandler;
+pub use password_handler::{PasswordChange, PasswordHandler};
+pub use vault_handler::VaultHandler;
@@ -1,57 +1,82 @@
-use powda_core::{Store, PasswordEntry, EntryName, Password, Result};
-use powda_core::repository::StoreRepository;
+use powda_core::{Store, VaultLocation, PasswordEntry, EntryMetadata, EntryName, EntryQuery, NameTree, Password, KdfParams, OtpSecret, Recipe, Result};
+use powda_core::domain::RetentionChange;
+use powda_core::export;
+use powda_core::credential::{self, CredentialRequest};
+use powda_core::search;
+use powda_core::ssh::SshKey;
+use powda_core::template::Template;
+use powda_core::import::{ArchiveImporter, BitwardenImporter, CsvDialect, CsvImporter, KdbxImporter, OnePuxImporter};
+use powda_core::{CollisionStrategy, Importer};
+use powda_core::repository::{atomic_write, StoreRepository};
 use powda_core::error::Error;
-use crate::ui;
+use crate::commands::{ExportTarget, GitOperation, ImportSource, KdfArgs, MetadataArgs};
+use crate::output::Output;
+use crate::{agent, clipboard, exec, ui};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+use std::time::{Duration, Instant, SystemTime};
+
+/// What `edit` should do with the password.
+pub enum PasswordChange {
+    Keep,
+    Prompt,
+    Generate(Recipe),
+}
+
+/// Fuzzy matches offered when a name is ambiguous.
+const MAX_CHOICES: usize = 10;
 
 pub struct PasswordHandler {
     store: Box<dyn StoreRepository>,
+    vault: VaultLocation,
+    output: Output,
 }
 
 impl PasswordHandler {
-    pub fn new() -> Self {
-        Self {
-            store: Box::new(Store::new()),
-        }
+    pub fn new(vault: VaultLocation, output: Output) -> Self {
+        // Talk to the agent when one is running so unlocks outlive this process.
+        let store: Box<dyn StoreRepository> = match agent::running(&vault) {
+            Some(client) => Box::new(client),
+            None => Box::new(Store::with_path(vault.path.clone())),
+        };
+        Self { store, vault, output }
+    }
+
+    /// Direct access to the vault file, bypassing the agent.
+    fn local_store(&self) -> Store {
+        Store::with_path(self.vault.path.clone())
     }
 
     async fn ensure_unlocked(&self ) -> Result<()> {
         if self.store.is_locked().await {
             let password = ui::prompt_password("Enter master password: ")?;
-            self.store.unlock(&password).await
-                .map_err(|_| Error::Encryption("Invalid Master password".to_string()))?;
+            self.store.unlock(&password).await.map_err(|e| match e {
+                Error::HeaderTampered => e,
+                _ => Error::Encryption("Invalid Master password".to_string()),
+            })?;
         }
         Ok(())
     }
 
-    pub async fn init(&self, force:bool) -> Result<()> {
+    pub async fn init(&self, force:bool, kdf: KdfArgs) -> Result<()> {
+        let kdf = kdf.resolve(KdfParams::default())?;
+
     if self.store.exists().await && !force {
-            println!("⚠️  Password vault already exists!");
-            println!("Use 'powda init --force' to reinitialize");
+            if !self.output.is_json() {
+                println!("⚠️  Password vault already exists!");
+                println!("Use 'powda init --force' to reinitialize");
+            }
             return Err(Error::AlreadyExists("Vault".to_string()));
         }
 
-        if force && self.store.exists().await {
-            println!("Force initializing will delete all existing passwords!");
-            println!("Are you sure? (y/N)");
-
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input).map_err(Error::from)?;
-
-            if input.trim().to_lowercase() != "y" {
-                println!("Cancelled.");
-                return Ok(())
-            }
-
-            let home = std::env::var("HOME").expect("HOME not set");
-
-            let old_path = std::path::PathBuf::from(&home).join(".powda_store.json");
-            let new_path = std::path::PathBuf::from(&home).join(".powda_vault.encrypted");
-
-            if old_path.exists() {
-                std::fs::remove_file(old_path).ok();
-            }
-            if new_path.exists() {
-                std::fs::remove_file(new_path).ok();
+        let replace = force && self.store.exists().await;
+        if replace {
+            eprintln!("Force initializing replaces all existing passwords with an empty vault!");
+            eprintln!("The old vault is kept as backup 1 ('powda backup restore 1').");
+            if !ui::confirm("Are you sure?")? {
+                return self.output.emit(json!({ "initialized": false }), || println!("Cancelled."));
             }
         }
         
@@ -68,93 +93,664 @@ impl PasswordHandler {
             return Err(Error::Encryption("Password must be at least 8 characters".to_string()));
         }
         
-        self.store.init(&password).await?;
-        println!("✅ Secure vault initialized!");
-        println!("⚠️  Remember your master password - it cannot be recovered!");
-        Ok(())
+        if replace {
+            self.local_store().reinit(&password, kdf)?;
+            // A running agent would otherwise keep serving the replaced vault.
+            self.store.lock().await.ok();
+        } else {
+            self.store.init(&password, kdf).await?;
+        }
+        self.output.emit(json!({ "initialized": true, "kdf": kdf }), || {
+            println!("✅ Secure vault initialized!");
+            println!("⚠️  Remember your master password - it cannot be recovered!");
+        })
     }
 
     pub async fn unlock(&self) -> Result<()> {
+        if !self.store.exists().await {
+            return Err(Error::NotInitialized);
+        }
+
         let password = ui::prompt_password("Enter master password: ")?;
-        self.store.unlock(&password).await?;
-        println!("Vault unlocked!");
-        Ok(())
+        let client = match agent::running(&self.vault) {
+            Some(client) => client,
+            None => agent::spawn(&self.vault)?,
+        };
+        client.unlock(&password).await?;
+        self.output.emit(json!({ "locked": false }), || {
+            println!("Vault unlocked! It stays unlocked until 'powda lock' or the agent's timeout.");
+        })
     }
 
-    pub async fn add(&self, name: String) -> Result<()> {
+    pub async fn add(&self, name: String, recipe: Option<Recipe>, metadata: MetadataArgs, otp: Option<String>) -> Result<()> {
+        let otp = otp.as_deref().map(OtpSecret::parse).transpose()?;
 
         self.ensure_unlocked().await?;
 
         let entry_name = EntryName::new(name.clone())
-            .map_err(|e| powda_core::Error::Encryption(e))?;
+            .map_err(powda_core::Error::Encryption)?;
+
+        let password = match &recipe {
+            Some(recipe) => recipe.generate()?,
+            None => {
+                let password = ui::prompt_password("Enter Password: ")?;
+                Password::new(password)
+                    .map_err(powda_core::Error::Encryption)?
+            }
+        };
 
+        let mut entry = PasswordEntry::new(entry_name, password);
+        metadata.apply(&mut entry.metadata);
+        entry.otp = otp;
+        self.store.add(entry).await?;
 
-        let password = ui::prompt_password("Enter Password: ")?;
-        let password = Password::new(password)
-            .map_err(|e| powda_core::Error::Encryption(e))?;
+        let entropy = recipe.as_ref().map(Recipe::entropy_bits).transpose()?;
+        self.output.emit(json!({ "name": name, "generated": recipe.is_some(), "entropy_bits": entropy }), || {
+            match entropy {
+                Some(bits) => println!("Generated password for '{}' added (~{:.0} bits)!", name, bits),
+                None => println!("Password for '{}' added!", name),
+            }
+        })
+    }
 
-        let entry = PasswordEntry::new(entry_name, password);
-        self.store.add(entry).await?;
+    pub async fn edit(&self, name: String, change: PasswordChange, metadata: MetadataArgs, untag: Vec<String>, otp: Option<String>) -> Result<()> {
+        if matches!(change, PasswordChange::Keep) && metadata.is_empty() && untag.is_empty() && otp.is_none() {
+            return Err(Error::InvalidInput("Nothing to change; see 'powda edit --help'".to_string()));
+        }
+        // Some("") clears the seed, anything else replaces it.
+        let otp = otp.map(|otp| (!otp.is_empty()).then(|| OtpSecret::parse(&otp)).transpose()).transpose()?;
 
-        println!("Password for '{}' added!", name);
-        Ok(())
+        self.ensure_unlocked().await?;
+
+        let entry_name = EntryName::new(name.clone())
+            .map_err(powda_core::Error::Encryption)?;
+        let mut entry = self.store.get(&entry_name).await?;
+        if entry.ssh.is_some() && !matches!(change, PasswordChange::Keep) {
+            return Err(Error::InvalidInput(format!(
+                "'{}' is an SSH key; replace it with 'powda ssh add --replace'", name
+            )));
+        }
+
+        let mut updated = entry.metadata.clone();
+        metadata.apply(&mut updated);
+        updated.tags.retain(|tag| !untag.contains(tag));
+        entry.update_metadata(updated);
+        if let Some(otp) = otp {
+            entry.update_otp(otp);
+        }
+
+        match change {
+            PasswordChange::Keep => {}
+            PasswordChange::Prompt => {
+                let password = ui::prompt_password("Enter new Password: ")?;
+                let password = Password::new(password)
+                    .map_err(powda_core::Error::Encryption)?;
+                entry.update_password(password);
+            }
+            PasswordChange::Generate(recipe) => entry.update_password(recipe.generate()?),
+        }
+
+        self.store.update(entry).await?;
+        self.output.emit(json!({ "name": name, "updated": true }), || println!("Entry '{}' updated!", name))
     }
 
-    pub async fn get(self, name: String) -> Result<()> {
+    /// Entry called `name`, or failing that the one `name` fuzzily
+    /// matches. Several matches are offered as a choice when someone is
+    /// at the terminal and reported as an error otherwise.
+    async fn find_entry(&self, name: &str) -> Result<PasswordEntry> {
+        if let Ok(entry_name) = EntryName::new(name.to_string()) {
+            match self.store.get(&entry_name).await {
+                Err(Error::NotFound(_)) => {}
+                found => return found,
+            }
+        }
+
+        let summaries = self.store.query(&EntryQuery::default()).await?;
+        let hits = search::search(name, &summaries);
+        let names: Vec<String> = hits.iter().take(MAX_CHOICES).map(|hit| hit.name.as_str().to_string()).collect();
+        let chosen = match names.len() {
+            0 => return Err(Error::NotFound(name.to_string())),
+            1 => Some(0),
+            _ if self.output.is_json() => None,
+            _ => ui::choose(&format!("'{}' matches several entries:", name), &names)?,
+        };
+        let Some(index) = chosen else {
+            let more = if hits.len() > names.len() { ", ..." } else { "" };
+            return Err(Error::InvalidInput(format!(
+                "'{}' matches several entries: {}{}", name, names.join(", "), more
+            )));
+        };
+
+        let entry = self.store.get(&hits[index].name).await?;
+        if names.len() == 1 && !self.output.is_json() {
+            eprintln!("Using '{}'", entry.name.as_str());
+        }
+        Ok(entry)
+    }
+
+    pub async fn show(&self, name: String, reveal: bool) -> Result<()> {
         self.ensure_unlocked().await?;
 
-        let entry_name = EntryName::new(name)
-            .map_err(|e| powda_core::Error::Encryption(e))?;
+        let entry = self.find_entry(&name).await?;
+        let metadata = &entry.metadata;
+        let value = json!({
+            "name": entry.name.as_str(),
+            "username": metadata.username,
+            "password": reveal.then(|| entry.password.as_str()),
+            "url": metadata.url,
+            "notes": metadata.notes,
+            "tags": metadata.tags,
+            "history_versions": entry.history.len(),
+            "otp": entry.otp.as_ref().map(|otp| json!({
+                "issuer": otp.issuer,
+                "account": otp.account,
+                "uri": reveal.then(|| otp.to_uri()),
+            })),
+            "ssh": entry.ssh.as_ref().map(|ssh| json!({
+                "public_key": ssh.public_key,
+                "fingerprint": ssh.fingerprint,
+                "confirm": ssh.confirm,
+            })),
+            "created_at": ui::format_rfc3339(entry.created_at),
+            "modified_at": ui::format_rfc3339(entry.modified_at),
+        });
+        self.output.emit(value, || Self::print_entry(&entry, reveal))
+    }
+
+    fn print_entry(entry: &PasswordEntry, reveal: bool) {
+        let field = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+
+        println!("Name:      {}", entry.name.as_str());
+        println!("Username:  {}", field(&entry.metadata.username));
+        if reveal {
+            println!("Password:  {}", entry.password.as_str());
+        } else {
+            println!("Password:  ******** (use --reveal to show)");
+        }
+        println!("URL:       {}", field(&entry.metadata.url));
+        println!("Tags:      {}", if entry.metadata.tags.is_empty() { "-".to_string() } else { entry.metadata.tags.join(", ") });
+        if let Some(otp) = &entry.otp {
+            if reveal {
+                println!("OTP:       {}", otp.to_uri());
+            } else {
+                println!("OTP:       configured (use 'powda otp {}' for a code)", entry.name.as_str());
+            }
+        }
+        if let Some(ssh) = &entry.ssh {
+            println!("SSH key:   {} {}", ssh.algorithm(), ssh.fingerprint);
+        }
+        if !entry.history.is_empty() {
+            println!("History:   {} previous password(s)", entry.history.len());
+        }
+        println!("Created:   {}", ui::format_time(entry.created_at));
+        println!("Modified:  {}", ui::format_time(entry.modified_at));
+        if let Some(notes) = &entry.metadata.notes {
+            println!("Notes:");
+            for line in notes.lines() {
+                println!("  {}", line);
+            }
+        }
+    }
+
+    pub async fn history(&self, name: String, reveal: bool) -> Result<()> {
+        self.ensure_unlocked().await?;
 
+        let entry_name = EntryName::new(name.clone())
+            .map_err(powda_core::Error::Encryption)?;
         let entry = self.store.get(&entry_name).await?;
-        println!("Password: {}", entry.password.as_str());
+        let shown = |password: &Password| if reveal { password.as_str().to_string() } else { "********".to_string() };
+
+        let versions: Vec<_> = entry.history.iter().enumerate().map(|(i, version)| json!({
+            "version": i + 1,
+            "replaced_at": ui::format_rfc3339(version.replaced_at),
+            "password": reveal.then(|| version.password.as_str()),
+        })).collect();
+
+        self.output.emit(json!({ "name": name, "versions": versions }), || {
+            if entry.history.is_empty() {
+                println!("No previous passwords for '{}'.", name);
+                return;
+            }
+            println!("Previous passwords for '{}' (newest first):", name);
+            for (i, version) in entry.history.iter().enumerate() {
+                println!(" {:>2}  replaced {}  {}", i + 1, ui::format_time(version.replaced_at), shown(&version.password));
+            }
+        })
+    }
 
-        // TODO: clipboard
-        println!("Clipboard support soon!");
+    pub async fn restore(&self, name: String, version: usize) -> Result<()> {
+        self.ensure_unlocked().await?;
 
-        Ok(())
+        let entry_name = EntryName::new(name.clone())
+            .map_err(powda_core::Error::Encryption)?;
+        let mut entry = self.store.get(&entry_name).await?;
+        entry.restore_version(version)?;
+        // An older SSH key has a different public half.
+        if let Some(confirm) = entry.ssh.as_ref().map(|ssh| ssh.confirm) {
+            entry.ssh = Some(SshKey::from_entry(&entry)?.info(confirm)?);
+        }
+        self.store.update(entry).await?;
+
+        self.output.emit(json!({ "name": name, "restored": version }), || {
+            println!("✅ Restored version {} of '{}'. The replaced password is now version 1.", version, name);
+        })
     }
 
-    pub async fn list(&self) -> Result<()> {
+    pub async fn retention(&self, max_versions: Option<usize>, max_age_days: Option<u32>) -> Result<()> {
         self.ensure_unlocked().await?;
 
-        let entries = self.store.list().await?;
-        if entries.is_empty() {
-            println!("No passwords stored.");
+        let change = RetentionChange { max_versions, max_age_days };
+        let changed = !change.is_empty();
+        let retention = if changed {
+            self.store.update_history_retention(change).await?
         } else {
-            println!("Stored password: ");
-            for entry_name in entries {
-                println!(" * {}", entry_name.as_str());
+            self.store.history_retention().await?
+        };
+
+        let value = json!({ "max_versions": retention.max_versions, "max_age_days": retention.max_age_days, "changed": changed });
+        self.output.emit(value, || {
+            if changed {
+                println!("✅ Retention updated; existing history was trimmed to match.");
+            }
+            println!("Versions kept per entry: {}", retention.max_versions);
+            match retention.max_age_days {
+                Some(days) => println!("Maximum age:             {} days", days),
+                None => println!("Maximum age:             unlimited"),
             }
+        })
+    }
+
+    pub async fn otp(&self, name: String) -> Result<()> {
+        self.ensure_unlocked().await?;
+
+        let mut entry = self.find_entry(&name).await?;
+        let name = entry.name.as_str().to_string();
+        let Some(otp) = entry.otp.as_mut() else {
+            return Err(Error::InvalidInput(format!(
+                "'{}' has no one-time code; add one with 'powda edit {} --otp <URI>'", name, name
+            )));
+        };
+
+        let code = otp.code(SystemTime::now())?;
+        // A counter-based code is spent once shown; never hand it out twice.
+        if otp.is_counter_based() {
+            otp.advance();
+            self.store.update(entry).await?;
         }
-        Ok(())
+
+        let remaining = code.remaining.map(|r| r.as_secs());
+        self.output.emit(json!({ "name": name, "code": code.code, "remaining_secs": remaining }), || {
+            match remaining {
+                Some(secs) => println!("{}  (valid for {}s)", code.code, secs),
+                None => println!("{}", code.code),
+            }
+        })
     }
 
-    pub async fn remove(&self, name: String) -> Result<()> {
+    pub async fn get(self, name: String, print: bool, clear_after: Option<Duration>) -> Result<()> {
         self.ensure_unlocked().await?;
 
-        let entry_name = EntryName::new(name.clone())
-            .map_err(|e| powda_core::Error::Encryption(e))?;
+        let entry = self.find_entry(&name).await?;
+        let name = entry.name.as_str();
+        if print {
+            let password = entry.password.as_str();
+            return self.output.emit(json!({ "name": name, "password": password }), || {
+                println!("Password: {}", password);
+            });
+        }
 
-        println!("Are you sure want to remove '{}'? (y/N", name);
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)
-            .map_err(powda_core::Error::from)?;
+        clipboard::copy(entry.password.as_str(), clear_after)?;
+        let clear_after = clear_after.map(|after| after.as_secs());
+        self.output.emit(json!({ "name": name, "copied": true, "clear_after": clear_after }), || {
+            match clear_after {
+                Some(after) => println!("📋 Copied '{}' to the clipboard, clearing in {}s.", name, after),
+                None => println!("📋 Copied '{}' to the clipboard.", name),
+            }
+        })
+    }
 
-        if input.trim().to_lowercase() == "y" {
-            self.store.remove(&entry_name).await?;
-            println!("Password for '{}' removed!", name);
+    pub async fn list(&self, prefix: Option<String>, tree: bool) -> Result<()> {
+        self.ensure_unlocked().await?;
+
+        let prefix = prefix.as_deref().unwrap_or("");
+        let entries: Vec<EntryName> = self.store.list().await?.into_iter()
+            .filter(|name| name.is_under(prefix))
+            .collect();
+        let names: Vec<&str> = entries.iter().map(EntryName::as_str).collect();
+        // Under a prefix the tree starts at that folder.
+        let folder = prefix.trim_end_matches('/');
+        let relative: Vec<EntryName> = match folder {
+            "" => entries.clone(),
+            _ => names.iter()
+                .filter_map(|name| name.strip_prefix(folder)?.strip_prefix('/'))
+                .filter_map(|name| EntryName::new(name.to_string()).ok())
+                .collect(),
+        };
+        let name_tree = NameTree::new(&relative);
+
+        let mut value = json!({ "entries": names });
+        if tree {
+            value["tree"] = tree_json(&name_tree);
+        }
+        self.output.emit(value, || {
+            if names.is_empty() && prefix.is_empty() {
+                println!("No passwords stored.");
+            } else if names.is_empty() {
+                println!("No passwords stored under '{}'.", prefix);
+            } else if tree {
+                println!("{}", if folder.is_empty() { "." } else { folder });
+                print_tree(&name_tree, "");
+            } else {
+                println!("Stored password: ");
+                for name in &names {
+                    println!(" * {}", name);
+                }
+            }
+        })
+    }
+
+    /// Run `command` with each `(VAR, ENTRY)` password in its environment
+    /// and return its exit code. Entries are looked up by exact name:
+    /// scripts should not depend on what a fuzzy match picks.
+    pub async fn run(&self, env: Vec<(String, String)>, mask: bool, command: Vec<String>) -> Result<i32> {
+        self.ensure_unlocked().await?;
+
+        let mut values = Vec::with_capacity(env.len());
+        for (var, name) in env {
+            let name = EntryName::new(name).map_err(Error::InvalidInput)?;
+            let entry = self.store.get(&name).await?;
+            values.push((var, Zeroizing::new(entry.password.as_str().to_string())));
+        }
+        exec::run(&command, &values, mask).await
+    }
+
+    /// Render `template` with secrets from the vault. Nothing is written
+    /// unless every reference resolves.
+    pub async fn inject(&self, template: PathBuf, output: Option<PathBuf>) -> Result<()> {
+        let text = if template.as_os_str() == "-" {
+            std::io::read_to_string(std::io::stdin())?
         } else {
-            println!("Cancelled!");
+            std::fs::read_to_string(&template)?
+        };
+        let parsed = Template::parse(&text)?;
+
+        self.ensure_unlocked().await?;
+        let mut entries = HashMap::new();
+        for reference in parsed.references() {
+            if entries.contains_key(&reference.name) {
+                continue;
+            }
+            match self.store.get(&reference.name).await {
+                Ok(entry) => { entries.insert(reference.name.clone(), entry); }
+                // Reported with its line by `render`.
+                Err(Error::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let rendered = Zeroizing::new(parsed.render(|name| entries.get(name))?);
+        let count = parsed.references().count();
+
+        let Some(output) = output else {
+            use std::io::Write;
+            let mut stdout = std::io::stdout();
+            stdout.write_all(rendered.as_bytes())?;
+            return Ok(stdout.flush()?);
+        };
+        atomic_write(&output, rendered.as_bytes())?;
+        self.output.emit(json!({ "path": output, "references": count }), || {
+            println!("✅ Wrote {} with {} secret reference(s) filled in", output.display(), count);
+        })
+    }
+
+    /// Speak git's credential helper protocol on stdin and stdout. Only
+    /// `get` ever prints; saving and erasing are limited to `store_folder`
+    /// so git cannot touch entries it did not create.
+    pub async fn git_credential(&self, operation: GitOperation, store_folder: Option<String>) -> Result<()> {
+        let request = CredentialRequest::parse(&std::io::read_to_string(std::io::stdin())?)?;
+        let store_folder = store_folder.filter(|folder| !folder.trim_matches('/').is_empty());
+        if matches!(operation, GitOperation::Store | GitOperation::Erase) && store_folder.is_none() {
+            return Ok(());
         }
+        self.ensure_unlocked().await?;
 
+        let summaries = self.store.query(&EntryQuery::default()).await?;
+        match operation {
+            GitOperation::Get => {
+                let Some(found) = request.best_match(&summaries) else {
+                    return Ok(());
+                };
+                let entry = self.store.get(&found.name).await?;
+                let reply = Zeroizing::new(credential::response(
+                    entry.metadata.username.as_deref(),
+                    entry.password.as_str(),
+                ).map_err(|e| match e {
+                    Error::InvalidInput(why) => Error::InvalidInput(format!("'{}': {}", found.name.as_str(), why)),
+                    e => e,
+                })?);
+                use std::io::Write;
+                let mut stdout = std::io::stdout();
+                stdout.write_all(reply.as_bytes())?;
+                stdout.flush()?;
+            }
+            GitOperation::Store => {
+                let (Some(folder), Some(password)) = (store_folder, request.password.as_deref()) else {
+                    return Ok(());
+                };
+                // Nothing to do if git used what `get` handed out.
+                if let Some(found) = request.best_match(&summaries)
+                    && self.store.get(&found.name).await?.password.as_str() == password {
+                    return Ok(());
+                }
+
+                let name = request.entry_name(&folder)?;
+                let password = Password::new(password.to_string()).map_err(Error::InvalidInput)?;
+                match self.store.get(&name).await {
+                    Ok(mut entry) => {
+                        entry.update_password(password);
+                        self.store.update(entry).await?;
+                    }
+                    Err(Error::NotFound(_)) => {
+                        let metadata = EntryMetadata {
+                            url: Some(request.url()),
+                            username: request.username.clone(),
+                            ..Default::default()
+                        };
+                        self.store.add(PasswordEntry::new(name, password).with_metadata(metadata)).await?;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            GitOperation::Erase => {
+                let Some(folder) = store_folder else {
+                    return Ok(());
+                };
+                for summary in &summaries {
+                    let Some(url) = summary.url.as_deref() else { continue };
+                    if !summary.name.is_under(&folder) || request.score(url, summary.username.as_deref()).is_none() {
+                        continue;
+                    }
+                    let entry = self.store.get(&summary.name).await?;
+                    // Only the password git says was rejected.
+                    if request.password.as_deref().is_none_or(|p| p == entry.password.as_str()) {
+                        self.store.remove(&summary.name).await?;
+                    }
+                }
+            }
+        }
         Ok(())
     }
+
+    /// Store the OpenSSH private key in `file` under `name`. An encrypted
+    /// key is decrypted first; the vault keeps it from then on.
+    pub async fn ssh_add(&self, name: String, file: PathBuf, confirm: bool, replace: bool) -> Result<()> {
+        let pem = Zeroizing::new(if file.as_os_str() == "-" {
+            std::io::read_to_string(std::io::stdin())?
+        } else {
+            std::fs::read_to_string(&file)?
+        });
+        let mut key = SshKey::parse(&pem)?;
+        if key.is_encrypted() {
+            key = key.decrypt(&Zeroizing::new(ui::prompt_password("Enter passphrase for the key: ")?))?;
+        }
+        let password = key.to_password()?;
+        let info = key.info(confirm)?;
+        let entry_name = EntryName::new(name.clone()).map_err(Error::InvalidInput)?;
+
+        self.ensure_unlocked().await?;
+        match self.store.get(&entry_name).await {
+            Ok(mut entry) if replace => {
+                entry.update_password(password);
+                entry.ssh = Some(info.clone());
+                self.store.update(entry).await?;
+            }
+            Ok(_) => return Err(Error::AlreadyExists(name)),
+            Err(Error::NotFound(_)) => {
+                let mut entry = PasswordEntry::new(entry_name, password);
+                entry.ssh = Some(info.clone());
+                self.store.add(entry).await?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        self.output.emit(json!({ "name": name, "algorithm": info.algorithm(), "fingerprint": info.fingerprint }), || {
+            println!("SSH key '{}' added ({} {})", name, info.algorithm(), info.fingerprint);
+        })
+    }
+
+    pub async fn ssh_list(&self) -> Result<()> {
+        self.ensure_unlocked().await?;
+
+        let keys: Vec<_> = self.store.query(&EntryQuery::default()).await?.into_iter()
+            .filter_map(|entry| Some((entry.name, entry.ssh?)))
+            .collect();
+        let value: Vec<_> = keys.iter().map(|(name, info)| json!({
+            "name": name.as_str(),
+            "algorithm": info.algorithm(),
+            "fingerprint": info.fingerprint,
+            "confirm": info.confirm,
+        })).collect();
+        self.output.emit(json!({ "keys": value }), || {
+            if keys.is_empty() {
+                println!("No SSH keys stored; add one with 'powda ssh add NAME FILE'.");
+            }
+            for (name, info) in &keys {
+                let confirm = if info.confirm { "  (confirm)" } else { "" };
+                println!("{}  {}  {}{}", info.fingerprint, info.algorithm(), name.as_str(), confirm);
+            }
+        })
+    }
+
+    /// Print the public key, ready for `authorized_keys`.
+    pub async fn ssh_export_public(&self, name: String) -> Result<()> {
+        self.ensure_unlocked().await?;
+
+        let entry = self.find_entry(&name).await?;
+        let info = entry.ssh.ok_or_else(|| Error::InvalidInput(format!("'{}' is not an SSH key", entry.name.as_str())))?;
+        self.output.emit(json!({ "name": entry.name.as_str(), "public_key": info.public_key }), || {
+            println!("{}", info.public_key);
+        })
+    }
+
+    pub async fn find(&self, query: EntryQuery) -> Result<()> {
+        self.ensure_unlocked().await?;
+
+        let found = self.store.query(&query).await?;
+        let value: Vec<_> = found.iter().map(|entry| json!({
+            "name": entry.name.as_str(),
+            "username": entry.username,
+            "url": entry.url,
+            "tags": entry.tags,
+            "otp": entry.has_otp,
+            "created_at": ui::format_rfc3339(entry.created_at),
+            "modified_at": ui::format_rfc3339(entry.modified_at),
+        })).collect();
+
+        self.output.emit(json!({ "entries": value }), || {
+            if found.is_empty() {
+                println!("No matching entries.");
+                return;
+            }
+            for entry in &found {
+                let mut details = Vec::new();
+                if let Some(username) = &entry.username {
+                    details.push(username.clone());
+                }
+                if let Some(url) = &entry.url {
+                    details.push(url.clone());
+                }
+                if !entry.tags.is_empty() {
+                    details.push(format!("[{}]", entry.tags.join(", ")));
+                }
+                println!("{:<32} {}", entry.name.as_str(), details.join("  "));
+            }
+        })
+    }
+
+    /// Rename an entry, or move a folder with everything in it. A target
+    /// ending in `/` keeps the name and moves it into that folder.
+    pub async fn rename(&self, from: String, to: String) -> Result<()> {
+        self.ensure_unlocked().await?;
+
+        let from = EntryName::new(from.trim_end_matches('/').to_string()).map_err(Error::InvalidInput)?;
+        let to = match to.strip_suffix('/') {
+            Some(folder) => format!("{}/{}", folder, from.leaf()),
+            None => to,
+        };
+        let to = EntryName::new(to).map_err(Error::InvalidInput)?;
+
+        let moved = self.store.rename(&from, &to).await?;
+        let value: Vec<_> = moved.iter()
+            .map(|(old, new)| json!({ "from": old.as_str(), "to": new.as_str() }))
+            .collect();
+        self.output.emit(json!({ "moved": value }), || {
+            for (old, new) in &moved {
+                println!("{} -> {}", old.as_str(), new.as_str());
+            }
+            println!("✅ Moved {} entr{}", moved.len(), if moved.len() == 1 { "y" } else { "ies" });
+        })
+    }
+
+    pub async fn remove(&self, name: String, yes: bool) -> Result<()> {
+        // Scripts cannot answer the question, and a "removed": false they
+        // did not ask for would look like success.
+        if self.output.is_json() && !yes {
+            return Err(Error::InvalidInput("pass --yes to remove an entry with --format json".to_string()));
+        }
+        self.ensure_unlocked().await?;
+
+        let entry_name = EntryName::new(name.clone())
+            .map_err(powda_core::Error::Encryption)?;
+
+        let removed = yes || ui::confirm(&format!("Are you sure want to remove '{}'?", name))?;
+        if removed {
+            self.store.remove(&entry_name).await?;
+        }
+
+        self.output.emit(json!({ "name": name, "removed": removed }), || {
+            if removed {
+                println!("Password for '{}' removed!", name);
+            } else {
+                println!("Cancelled!");
+            }
+        })
+    }
+
     pub async fn lock(&self) -> Result<()> {
-    self.store.lock().await?;
-    println!("Vault locked!");
-    Ok(())
-}
+        self.store.lock().await?;
+        self.output.emit(json!({ "locked": true }), || println!("Vault locked!"))
+    }
+
+    pub async fn stop_agent(&self) -> Result<()> {
+        let client = agent::running(&self.vault);
+        if let Some(client) = &client {
+            client.shutdown()?;
+        }
+
+        self.output.emit(json!({ "stopped": client.is_some() }), || match client {
+            Some(_) => println!("Agent stopped, vault locked."),
+            None => println!("No agent running."),
+        })
+    }
 
     pub async fn change_master(&self) -> Result<()> {
         self.ensure_unlocked().await?;
@@ -168,7 +764,198 @@ impl PasswordHandler {
         }
     
         self.store.change_master_password(&current, &new).await?;
-        println!("Master password changed successfully!");
-        Ok(())
+        self.output.emit(json!({ "changed": true }), || println!("Master password changed successfully!"))
+    }
+
+    pub async fn import(&self, file: PathBuf, source: ImportSource, strategy: CollisionStrategy) -> Result<()> {
+        let data = std::fs::read(&file)?;
+        let importer: Box<dyn Importer> = match source {
+            ImportSource::Archive => Box::new(ArchiveImporter::new(&ui::prompt_password("Archive password: ")?)),
+            ImportSource::Kdbx => Box::new(KdbxImporter::new(&ui::prompt_password("KeePass database password: ")?)),
+            ImportSource::BitwardenJson => Box::new(BitwardenImporter),
+            ImportSource::OnePux => Box::new(OnePuxImporter),
+            ImportSource::ChromeCsv => Box::new(CsvImporter::new(CsvDialect::Chrome)),
+            ImportSource::FirefoxCsv => Box::new(CsvImporter::new(CsvDialect::Firefox)),
+            ImportSource::GenericCsv => Box::new(CsvImporter::new(CsvDialect::Generic)),
+        };
+        let batch = importer.parse(&data)?;
+
+        self.ensure_unlocked().await?;
+        let report = self.store.import(batch.entries, strategy).await?;
+
+        let renamed: Vec<_> = report.renamed.iter()
+            .map(|(from, to)| json!({ "from": from, "to": to }))
+            .collect();
+        let value = json!({
+            "added": report.added,
+            "overwritten": report.overwritten,
+            "renamed": renamed,
+            "skipped": report.skipped,
+            "unsupported": batch.unsupported,
+            "invalid_otp": batch.invalid_otp.iter()
+                .map(|(name, reason)| json!({ "name": name, "reason": reason }))
+                .collect::<Vec<_>>(),
+        });
+        self.output.emit(value, || {
+            println!("✅ Imported {} entries from {}", report.added.len() + report.renamed.len(), file.display());
+            if !report.overwritten.is_empty() {
+                println!("Overwritten ({}): {}", report.overwritten.len(), report.overwritten.join(", "));
+            }
+            for (from, to) in &report.renamed {
+                println!("Renamed: {} → {}", from, to);
+            }
+            if !report.skipped.is_empty() {
+                println!("Skipped, name already taken ({}): {}", report.skipped.len(), report.skipped.join(", "));
+            }
+            if !batch.unsupported.is_empty() {
+                println!("Not imported, no password ({}): {}", batch.unsupported.len(), batch.unsupported.join(", "));
+            }
+            for (name, reason) in &batch.invalid_otp {
+                println!("Imported without its one-time code: {} ({})", name, reason);
+            }
+        })
+    }
+
+    pub async fn export(&self, file: PathBuf, target: ExportTarget, unencrypted: bool, kdf: KdfArgs) -> Result<()> {
+        let format = target.plaintext_format();
+        if format.is_some() && !unencrypted {
+            return Err(Error::InvalidInput(
+                "this format writes every password in plaintext; pass --unencrypted to confirm".to_string(),
+            ));
+        }
+        let kdf = kdf.resolve(KdfParams::default())?;
+
+        if file.exists() && !ui::confirm(&format!("{} already exists. Overwrite it?", file.display()))? {
+            return self.output.emit(json!({ "exported": false }), || println!("Cancelled."));
+        }
+
+        self.ensure_unlocked().await?;
+        let entries = self.store.entries().await?;
+
+        let contents = match format {
+            Some(format) => export::export(&entries, format)?,
+            None => {
+                let password = ui::prompt_password("Archive password: ")?;
+                if password != ui::prompt_password("Confirm archive password: ")? {
+                    return Err(Error::Encryption("Passwords don't match".to_string()));
+                }
+                if password.len() < 8 {
+                    return Err(Error::Encryption("Password must be at least 8 characters".to_string()));
+                }
+                export::seal_archive(&entries, &password, kdf)?
+            }
+        };
+        atomic_write(&file, &contents)?;
+
+        let value = json!({ "exported": true, "file": file, "entries": entries.len(), "encrypted": format.is_none() });
+        self.output.emit(value, || {
+            println!("✅ Exported {} entries to {}", entries.len(), file.display());
+            if format.is_some() {
+                println!("⚠️  The file is not encrypted; delete it once you are done with it.");
+            }
+        })
+    }
+
+    pub async fn generate(&self, recipe: Recipe, count: usize) -> Result<()> {
+        let passwords = (0..count).map(|_| recipe.generate()).collect::<Result<Vec<_>>>()?;
+        let passwords: Vec<&str> = passwords.iter().map(Password::as_str).collect();
+        let entropy = recipe.entropy_bits()?;
+
+        self.output.emit(json!({ "passwords": passwords, "entropy_bits": entropy }), || {
+            for password in &passwords {
+                println!("{}", password);
+            }
+            // On stderr so `$(powda generate)` captures only the password.
+            eprintln!("~{:.0} bits of entropy each", entropy);
+        })
     }
+
+    pub async fn kdf_show(&self) -> Result<()> {
+        let kdf = self.local_store().kdf_params()?;
+        self.output.emit(json!(kdf), || {
+            println!("Argon2id memory:      {} KiB", kdf.memory_kib);
+            println!("Argon2id iterations:  {}", kdf.iterations);
+            println!("Argon2id parallelism: {}", kdf.parallelism);
+        })
+    }
+
+    pub async fn kdf_upgrade(&self, kdf: KdfArgs) -> Result<()> {
+        let current = self.local_store().kdf_params()?;
+        let kdf = kdf.resolve(current)?;
+        if kdf == current {
+            return self.output.emit(json!({ "kdf": kdf, "changed": false }), || {
+                println!("Vault already uses these parameters.");
+            });
+        }
+
+        let password = ui::prompt_password("Enter master password: ")?;
+        let started = Instant::now();
+        self.store.upgrade_kdf(&password, kdf).await?;
+        let elapsed = started.elapsed().as_millis();
+
+        self.output.emit(json!({ "kdf": kdf, "changed": true, "elapsed_ms": elapsed }), || {
+            println!("✅ Key re-derived with {} KiB, {} iterations, {} lanes ({} ms)",
+                kdf.memory_kib, kdf.iterations, kdf.parallelism, elapsed);
+        })
+    }
+
+    pub async fn backup_list(&self) -> Result<()> {
+        let backups = self.local_store().backups()?;
+        let value: Vec<_> = backups.iter().map(|backup| json!({
+            "index": backup.index,
+            "path": backup.path,
+            "modified": ui::format_rfc3339(backup.modified),
+            "size": backup.size,
+        })).collect();
+
+        self.output.emit(json!({ "backups": value }), || {
+            if backups.is_empty() {
+                println!("No backups yet.");
+                return;
+            }
+
+            println!("Backups (newest first):");
+            for backup in &backups {
+                println!(" {:>2}  {}  {} bytes", backup.index, ui::format_time(backup.modified), backup.size);
+            }
+        })
+    }
+
+    pub async fn backup_restore(&self, index: usize) -> Result<()> {
+        if !ui::confirm(&format!("Replace the current vault with backup {}?", index))? {
+            return self.output.emit(json!({ "restored": null }), || println!("Cancelled."));
+        }
+
+        self.local_store().restore_backup(index)?;
+        // The agent still holds the replaced state; make it unlock again.
+        self.store.lock().await?;
+
+        self.output.emit(json!({ "restored": index }), || {
+            println!("✅ Restored backup {}. The previous vault is now backup 1.", index);
+        })
+    }
+}
+
+/// Draw `tree` below a root line the way `tree(1)` does.
+fn print_tree(tree: &NameTree, indent: &str) {
+    let folders: Vec<_> = tree.folders().collect();
+    let entries: Vec<_> = tree.entries().collect();
+    let count = folders.len() + entries.len();
+
+    for (i, (name, folder)) in folders.iter().enumerate() {
+        let last = i + 1 == count;
+        println!("{}{}{}/", indent, if last { "└── " } else { "├── " }, name);
+        print_tree(folder, &format!("{}{}", indent, if last { "    " } else { "│   " }));
+    }
+    for (i, name) in entries.iter().enumerate() {
+        let last = folders.len() + i + 1 == count;
+        println!("{}{}{}", indent, if last { "└── " } else { "├── " }, name);
+    }
+}
+
+fn tree_json(tree: &NameTree) -> Value {
+    let folders: serde_json::Map<String, Value> = tree.folders()
+        .map(|(name, folder)| (name.to_string(), tree_json(folder)))
+        .collect();
+    json!({ "folders": folders, "entries": tree.entries().collect::<Vec<_>>() })
 }
@@ -0,0 +1,127 @@
+use powda_core::error::Error;
+use powda_core::repository::StoreRepository;
+use powda_core::{KdfParams, Result, Store, VaultRegistry};
+use crate::agent;
+use crate::commands::KdfArgs;
+use crate::output::Output;
+use crate::ui;
+use serde_json::json;
+use std::path::PathBuf;
+
+pub struct VaultHandler {
+    output: Output,
+}
+
+impl VaultHandler {
+    pub fn new(output: Output) -> Self {
+        Self { output }
+    }
+
+    pub async fn list(&self) -> Result<()> {
+        let registry = VaultRegistry::load()?;
+        let default = registry.default_name().to_string();
+        let vaults = registry.list()?;
+
+        let value: Vec<_> = vaults.iter().map(|vault| json!({
+            "name": vault.name,
+            "path": vault.path,
+            "default": vault.name.as_deref() == Some(default.as_str()),
+            "initialized": vault.path.exists(),
+            "agent": agent::running(vault).is_some(),
+        })).collect();
+
+        self.output.emit(json!({ "vaults": value }), || {
+            for vault in &vaults {
+                let marker = if vault.name.as_deref() == Some(default.as_str()) { "*" } else { " " };
+                let state = match (vault.path.exists(), agent::running(vault).is_some()) {
+                    (false, _) => " (not initialized)",
+                    (true, true) => " (agent running)",
+                    (true, false) => "",
+                };
+                println!("{} {:<16} {}{}", marker, vault.label(), vault.path.display(), state);
+            }
+        })
+    }
+
+    pub async fn create(&self, name: String, path: Option<PathBuf>, kdf: KdfArgs) -> Result<()> {
+        let kdf = kdf.resolve(KdfParams::default())?;
+        let mut registry = VaultRegistry::load()?;
+        let vault = registry.add(&name, path)?;
+
+        // Registering an existing vault file, e.g. one shared by a team.
+        let initialized = !vault.path.exists();
+        if initialized {
+            let password = ui::prompt_password("Enter master password for the new vault: ")?;
+            if password != ui::prompt_password("Confirm master password: ")? {
+                return Err(Error::Encryption("Passwords don't match".to_string()));
+            }
+            if password.len() < 8 {
+                return Err(Error::Encryption("Password must be at least 8 characters".to_string()));
+            }
+            Store::with_path(vault.path.clone()).init(&password, kdf).await?;
+        }
+        registry.save()?;
+
+        let value = json!({ "name": name, "path": vault.path, "initialized": initialized });
+        self.output.emit(value, || {
+            match initialized {
+                true => println!("✅ Created vault '{}' at {}", name, vault.path.display()),
+                false => println!("✅ Registered existing vault '{}' at {}", name, vault.path.display()),
+            }
+            println!("Use it with 'powda --vault {} ...' or 'powda vault default {}'.", name, name);
+        })
+    }
+
+    pub async fn remove(&self, name: String, delete: bool) -> Result<()> {
+        let mut registry = VaultRegistry::load()?;
+        let vault = registry.remove(&name)?;
+
+        if delete {
+            eprintln!("This deletes {} and its backups; the passwords in it cannot be recovered.", vault.path.display());
+            if !ui::confirm("Are you sure?")? {
+                return self.output.emit(json!({ "removed": false }), || println!("Cancelled."));
+            }
+        }
+
+        if let Some(client) = agent::running(&vault) {
+            client.shutdown()?;
+        }
+        if delete {
+            let store = Store::with_path(vault.path.clone());
+            for backup in store.backups()? {
+                std::fs::remove_file(backup.path)?;
+            }
+            std::fs::remove_file(&vault.path).ok();
+            let mut lock = vault.path.clone().into_os_string();
+            lock.push(".lock");
+            std::fs::remove_file(lock).ok();
+        }
+        registry.save()?;
+
+        let value = json!({ "removed": true, "name": name, "path": vault.path, "deleted": delete });
+        self.output.emit(value, || {
+            println!("✅ Removed vault '{}'", name);
+            if !delete {
+                println!("The file was kept at {}", vault.path.display());
+            }
+        })
+    }
+
+    pub async fn default(&self, name: Option<String>) -> Result<()> {
+        let mut registry = VaultRegistry::load()?;
+        if let Some(name) = &name {
+            registry.set_default(name)?;
+            registry.save()?;
+        }
+
+        let default = registry.get(registry.default_name())?;
+        let value = json!({ "default": default.name, "path": default.path, "changed": name.is_some() });
+        self.output.emit(value, || {
+            if name.is_some() {
+                println!("✅ Default vault is now '{}'", default.label());
+            } else {
+                println!("{} ({})", default.label(), default.path.display());
+            }
+        })
+    }
+}
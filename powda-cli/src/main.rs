@@ -1,29 +1,130 @@
+mod agent;
+mod clipboard;
 mod commands;
+mod config;
+mod exec;
 mod handlers;
+mod output;
+mod ssh_agent;
+mod tui;
 mod ui;
 
 use clap::Parser;
-use commands::{Cli, Commands};
-use handlers::PasswordHandler;
+use commands::{BackupCommand, Cli, Commands, ConfigCommand, KdfCommand, SshCommand, VaultCommand};
+use config::Config;
+use handlers::{ConfigHandler, PasswordChange, PasswordHandler, VaultHandler};
+use output::Output;
+use powda_core::{Result, VaultRegistry};
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
-    let handler = PasswordHandler::new();
+    // A broken config should not hide its own error, so fall back to the
+    // default format and report it from run() instead.
+    let config = Config::load();
+    let format = cli.format.or(config.as_ref().ok().map(|config| config.format)).unwrap_or_default();
+    let output = Output::new(format);
 
-    let result = match cli.command {
-        Commands::Init {force} => handler.init(force).await,
-        Commands::Add {name} => handler.add(name).await,
-        Commands::Get {name} => handler.get(name).await,
-        Commands::List => handler.list().await,
-        Commands::Remove {name} => handler.remove(name).await,
+    if let Err(e) = run(cli, config, output).await {
+        output.error(&e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli, config: Result<Config>, output: Output) -> Result<()> {
+    // These work without a selected vault, so a bad --vault cannot block
+    // them, and `config` works without a valid config so it can repair it.
+    match cli.command {
+        Commands::Config {command} => {
+            let settings = ConfigHandler::new(output);
+            return match command {
+                ConfigCommand::List => settings.list().await,
+                ConfigCommand::Get {key} => settings.get(key).await,
+                ConfigCommand::Set {key, value} => settings.set(key, value).await,
+                ConfigCommand::Edit => settings.edit().await,
+            };
+        }
+        Commands::Vault {command} => {
+            let vaults = VaultHandler::new(output);
+            return match command {
+                VaultCommand::List => vaults.list().await,
+                VaultCommand::Create {name, path, kdf} => vaults.create(name, path, kdf).await,
+                VaultCommand::Remove {name, delete} => vaults.remove(name, delete).await,
+                VaultCommand::Default {name} => vaults.default(name).await,
+            };
+        }
+        Commands::ClipboardClear {after} => return clipboard::clear_after(Duration::from_secs(after)),
+        _ => {}
+    }
+
+    let config = config?;
+    let vault = VaultRegistry::load()?.resolve(cli.vault.or(config.vault).as_deref())?;
+    let handler = PasswordHandler::new(vault.clone(), output);
+
+    match cli.command {
+        Commands::Init {force, kdf} => handler.init(force, kdf).await,
+        Commands::Add {name, generate, generator, metadata, otp} => {
+            handler.add(name, generate.then(|| generator.recipe(&config.generator)), metadata, otp).await
+        }
+        Commands::Edit {name, password, generate, generator, metadata, untag, otp} => {
+            let change = match (password, generate) {
+                (_, true) => PasswordChange::Generate(generator.recipe(&config.generator)),
+                (true, false) => PasswordChange::Prompt,
+                (false, false) => PasswordChange::Keep,
+            };
+            handler.edit(name, change, metadata, untag, otp).await
+        }
+        Commands::Otp {name} => handler.otp(name).await,
+        Commands::History {name, reveal} => handler.history(name, reveal).await,
+        Commands::Restore {name, version} => handler.restore(name, version).await,
+        Commands::Retention {max_versions, max_age_days} => handler.retention(max_versions, max_age_days).await,
+        Commands::Show {name, reveal} => handler.show(name, reveal).await,
+        Commands::Get {name, print, clear_after, ..} => {
+            let clear_after = clear_after.unwrap_or(config.clipboard.clear_after);
+            handler.get(name, print, (clear_after > 0).then(|| Duration::from_secs(clear_after))).await
+        }
+        Commands::List {prefix, tree} => handler.list(prefix, tree).await,
+        Commands::Remove {name, yes} => handler.remove(name, yes).await,
+        Commands::Run {env, no_mask, command} => {
+            let code = handler.run(env, !no_mask, command).await?;
+            std::process::exit(code)
+        }
+        Commands::Inject {template, output} => handler.inject(template, output).await,
+        Commands::GitCredential {operation} => handler.git_credential(operation, config.git.store_folder).await,
+        Commands::Ssh {command: SshCommand::Add {name, file, confirm, replace}} => handler.ssh_add(name, file, confirm, replace).await,
+        Commands::Ssh {command: SshCommand::List} => handler.ssh_list().await,
+        Commands::Ssh {command: SshCommand::ExportPublic {name}} => handler.ssh_export_public(name).await,
+        Commands::SshAgent {socket, confirm} => ssh_agent::serve(&vault, socket, confirm).await,
+        Commands::Find {query} => handler.find(query.query()).await,
+        Commands::Mv {from, to} => handler.rename(from, to).await,
         Commands::Unlock => handler.unlock().await,
         Commands::Lock => handler.lock().await,
         Commands::ChangeMaster => handler.change_master().await,
-    };
-
-    if let Err(e) = result {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+        Commands::Generate {generator, count} => handler.generate(generator.recipe(&config.generator), count).await,
+        Commands::Import {file, from, on_conflict} => handler.import(file, from, on_conflict.strategy()).await,
+        Commands::Export {file, to, unencrypted, kdf} => handler.export(file, to, unencrypted, kdf).await,
+        Commands::Kdf {command: KdfCommand::Show} => handler.kdf_show().await,
+        Commands::Kdf {command: KdfCommand::Upgrade {kdf}} => handler.kdf_upgrade(kdf).await,
+        Commands::Backup {command: BackupCommand::List} => handler.backup_list().await,
+        Commands::Backup {command: BackupCommand::Restore {index}} => handler.backup_restore(index).await,
+        Commands::Tui {idle_timeout, max_lifetime, generator} => {
+            let idle_timeout = idle_timeout.unwrap_or(config.agent.idle_timeout);
+            let max_lifetime = max_lifetime.unwrap_or(config.agent.max_lifetime);
+            let clear_after = config.clipboard.clear_after;
+            tui::run(
+                &vault,
+                agent::session_policy(idle_timeout, max_lifetime),
+                generator.recipe(&config.generator),
+                (clear_after > 0).then(|| Duration::from_secs(clear_after)),
+            ).await
+        }
+        Commands::Agent {stop: true, ..} => handler.stop_agent().await,
+        Commands::Agent {idle_timeout, max_lifetime, ..} => {
+            let idle_timeout = idle_timeout.unwrap_or(config.agent.idle_timeout);
+            let max_lifetime = max_lifetime.unwrap_or(config.agent.max_lifetime);
+            agent::serve(&vault, agent::session_policy(idle_timeout, max_lifetime)).await
+        }
+        Commands::Config {..} | Commands::Vault {..} | Commands::ClipboardClear {..} => unreachable!("handled above"),
     }
 }
@@ -0,0 +1,48 @@
+use clap::ValueEnum;
+use powda_core::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// Where command results go: human text, or exactly one JSON document on
+/// stdout per command so scripts can parse it.
+#[derive(Clone, Copy)]
+pub struct Output {
+    format: OutputFormat,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format }
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.format == OutputFormat::Json
+    }
+
+    /// Print `value` in JSON mode, otherwise run `human`.
+    pub fn emit(&self, value: Value, human: impl FnOnce()) -> Result<()> {
+        if self.is_json() {
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        } else {
+            human();
+        }
+        Ok(())
+    }
+
+    pub fn error(&self, e: &Error) {
+        if self.is_json() {
+            let value = json!({ "error": { "code": e.code(), "message": e.to_string() } });
+            println!("{}", value);
+        } else {
+            eprintln!("Error: {}", e);
+        }
+    }
+}
@@ -1,44 +1,72 @@
+use crate::clipboard;
 use crate::tui::{
     events::EventHandler,
-    types::{AppContext, AppState, InputMode, MessageType, Result, TuiError},
+    types::{AppContext, AppState, InputMode, MessageType, Result},
     ui::UI,
 };
-use powda_core::{Store, StoreRepository, PasswordEntry, EntryName, Password};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use powda_core::{StoreRepository, PasswordEntry, EntryName, EntryQuery, EntrySummary, NameTree, OtpSecret, Password, Recipe};
+use powda_core::domain::{OtpCode, TreeRow, TreeRowKind};
+use powda_core::repository::{SessionClock, SessionPolicy};
+use powda_core::search::{self, MatchField};
 use ratatui::{backend::Backend, Terminal};
-use std::time::Duration;
-use tui_input::Input;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime};
+use tui_input::{Input, InputRequest};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Puts a secret on the clipboard, clearing it after the delay.
+type CopyFn = fn(&str, Option<Duration>) -> powda_core::Result<()>;
 
 pub struct App {
     store: Box<dyn StoreRepository>,
     context: AppContext,
     entries: Vec<String>,
+    /// Secret-free view of every entry, for searching.
+    summaries: Vec<EntrySummary>,
+    /// Search matches in ranked order, or every entry when not searching.
     filtered_entries: Vec<String>,
+    /// Matched character positions of names, for highlighting.
+    highlights: HashMap<String, Vec<usize>>,
+    /// Visible lines of the list pane: folders and the entries of
+    /// expanded folders. `selected_index` points into this.
+    rows: Vec<TreeRow>,
+    collapsed: HashSet<String>,
     selected_index: usize,
     input: Input,
     password_input: String,
     search_query: String,
     show_password: bool,
     event_handler: EventHandler,
-    ui:UI
+    ui:UI,
+    policy: SessionPolicy,
+    session: Option<SessionClock>,
+    recipe: Recipe,
+    clear_after: Option<Duration>,
+    copy: CopyFn,
+    viewed: Option<EntrySummary>,
+    viewed_otp: Option<OtpSecret>,
 }
 
 impl App {
-    pub async fn new() -> Result<Self> {
-        let store = Box::new(Store::new());
-
+    pub async fn new(store: Box<dyn StoreRepository>, policy: SessionPolicy, recipe: Recipe, clear_after: Option<Duration>) -> Result<Self> {
         if !store.exists().await {
-            return Err(TuiError::NoVault);
+            return Err(powda_core::Error::NotInitialized.into());
         }
 
         Ok(Self {
             store,
             context: AppContext {
-                state: AppState:Locked,
+                state: AppState::Locked,
                 input_mode: InputMode::MasterPassword,
                 message: None,
             },
             entries: Vec::new(),
+            summaries: Vec::new(),
             filtered_entries: Vec::new(),
+            highlights: HashMap::new(),
+            rows: Vec::new(),
+            collapsed: HashSet::new(),
             selected_index: 0,
             input: Input::default(),
             password_input: String::new(),
@@ -46,51 +74,86 @@ impl App {
             show_password: false,
             event_handler: EventHandler::new(),
             ui: UI::new(),
+            policy,
+            session: None,
+            recipe,
+            clear_after,
+            copy: clipboard::copy,
+            viewed: None,
+            viewed_otp: None,
         })
     }
 
-    pub async fn run<B: Backend>(mut self, terminal: &mut Terminal<B>) -> Result<()> {
+    pub async fn run<B: Backend<Error = std::io::Error>>(mut self, terminal: &mut Terminal<B>) -> Result<()> {
         loop{
             terminal.draw(|f| self.ui.render(f, &self))?;
 
             if let Some(event) = self.event_handler.next(Duration::from_millis(100))? {
-                if self.handle_event(event).await? {
-                    break;
+                match self.handle_event(event).await {
+                    Ok(true) => break,
+                    Ok(false) => {}
+                    // A failed action is reported, not fatal.
+                    Err(e) => self.set_message(&e.to_string(), MessageType::Error),
                 }
             }
+
+            self.tick().await?;
         }
+        self.store.lock().await?;
         Ok(())
     }
 
-    async fn handle_event(&mut self, event: crossterm::event::Event) -> Result<bool> {
-        use crossterm::event::{Event, KeyCode, KeyEventKind};
+    /// Called between events: locks the vault once the session has been
+    /// idle, or open, for longer than the policy allows.
+    pub async fn tick(&mut self) -> Result<()> {
+        if self.session.is_some_and(|clock| clock.is_expired(&self.policy)) {
+            self.auto_lock().await?;
+        }
+        Ok(())
+    }
 
+    /// Apply one terminal event; returns true when the app should quit.
+    /// Any event counts as activity for the idle timeout.
+    pub async fn handle_event(&mut self, event: Event) -> Result<bool> {
+        if let Some(clock) = self.session.as_mut() {
+            clock.touch();
+        }
         if let Event::Key(key) = event {
             if key.kind != KeyEventKind::Press {
                 return Ok(false);
             }
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                return Ok(true);
+            }
 
             // Delegate to specific handlers
-            match self.context.input_mode {
-                // InputMode::Normal 
-                // InputMode::Normal 
-                // InputMode::Normal 
-                // InputMode::Normal 
-                // InputMode::Normal 
-            }
+            return match self.context.input_mode {
+                InputMode::MasterPassword => self.handle_master_password_mode(key.code).await,
+                InputMode::EntryName => self.handle_entry_name_mode(key.code),
+                InputMode::EntryPassword => self.handle_entry_password_mode(key).await,
+                InputMode::Search => self.handle_search_mode(key.code).await,
+                InputMode::Normal => match self.context.state {
+                    AppState::Help => {
+                        self.return_to_main();
+                        Ok(false)
+                    }
+                    AppState::ConfirmDelete(_) => self.handle_confirm_delete(key.code).await,
+                    _ => self.handle_normal_mode(key.code).await,
+                },
+            };
         }
         Ok(false)
     }
 
     async fn handle_normal_mode(&mut self, key: KeyCode) -> Result<bool> {
-        use crossterm::event::KeyCode;
-
         match key {
             KeyCode::Char('q') | KeyCode::Char('Q') => return Ok(true),
             KeyCode::Char('a') => self.start_add_entry(),
             KeyCode::Char('/') => self.start_search(),
             KeyCode::Char('?') => self.show_help(),
             KeyCode::Enter => self.view_selected_entry().await?,
+            KeyCode::Left | KeyCode::Char('h') => self.set_selected_folder_collapsed(true),
+            KeyCode::Right | KeyCode::Char('l') => self.set_selected_folder_collapsed(false),
             KeyCode::Char('d') | KeyCode::Delete => self.confirm_delete_selected(),
 
             KeyCode::Up | KeyCode::Char('k') => self.move_selection_up(),
@@ -102,34 +165,354 @@ impl App {
         Ok(false)
     }
 
+    pub fn context(&self) -> &AppContext {
+        &self.context
+    }
+
+    pub fn rows(&self) -> &[TreeRow] {
+        &self.rows
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    pub fn search_query(&self) -> &str {
+        &self.search_query
+    }
+
+    /// Name typed in the add form.
+    pub fn entry_name_input(&self) -> &Input {
+        &self.input
+    }
+
+    /// Master password or new entry password, masked unless shown.
+    pub fn password_display(&self) -> String {
+        match self.show_password {
+            true => self.password_input.clone(),
+            false => "*".repeat(self.password_input.chars().count()),
+        }
+    }
+
+    /// Entry open in the detail pane.
+    pub fn viewed(&self) -> Option<&EntrySummary> {
+        self.viewed.as_ref()
+    }
+
+    /// Characters of `name` matched by the current search.
+    pub fn highlight(&self, name: &str) -> &[usize] {
+        self.highlights.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Rebuild the visible rows after the entries, the search filter or
+    /// the collapsed folders change.
+    fn refresh_rows(&mut self) {
+        if !self.search_query.is_empty() {
+            // Matches are listed flat, best first, so ranking is visible.
+            self.rows = self.filtered_entries.iter().map(|name| TreeRow {
+                path: name.clone(),
+                label: name.clone(),
+                depth: 0,
+                kind: TreeRowKind::Entry,
+            }).collect();
+        } else {
+            let names: Vec<EntryName> = self.filtered_entries.iter()
+                .filter_map(|name| EntryName::new(name.clone()).ok())
+                .collect();
+            self.rows = NameTree::new(&names).rows(&self.collapsed);
+        }
+        self.selected_index = self.selected_index.min(self.rows.len().saturating_sub(1));
+    }
+
+    fn move_selection_up(&mut self) {
+        self.selected_index = self.selected_index.saturating_sub(1);
+    }
+
+    fn move_selection_down(&mut self) {
+        if self.selected_index + 1 < self.rows.len() {
+            self.selected_index += 1;
+        }
+    }
+
+    /// Back to the list from any form, view or prompt.
+    fn return_to_main(&mut self) {
+        self.context.state = AppState::Main;
+        self.context.input_mode = InputMode::Normal;
+        self.input.reset();
+        self.password_input.zeroize();
+        self.show_password = false;
+        self.viewed = None;
+        self.viewed_otp = None;
+    }
+
+    fn show_help(&mut self) {
+        self.context.state = AppState::Help;
+    }
+
+    fn start_search(&mut self) {
+        self.context.state = AppState::Search;
+        self.context.input_mode = InputMode::Search;
+    }
+
+    /// Typing in the `/` prompt narrows the list as you go; Enter keeps
+    /// the matches and returns to the list, Esc clears the search.
+    async fn handle_search_mode(&mut self, key: KeyCode) -> Result<bool> {
+        match key {
+            KeyCode::Char(c) => self.search_query.push(c),
+            KeyCode::Backspace => { self.search_query.pop(); }
+            KeyCode::Enter => {
+                self.context.state = AppState::Main;
+                self.context.input_mode = InputMode::Normal;
+                return Ok(false);
+            }
+            KeyCode::Esc => {
+                self.search_query.clear();
+                self.context.state = AppState::Main;
+                self.context.input_mode = InputMode::Normal;
+            }
+            _ => return Ok(false),
+        }
+        self.update_search();
+        Ok(false)
+    }
+
+    fn update_search(&mut self) {
+        self.highlights.clear();
+        if self.search_query.is_empty() {
+            self.filtered_entries = self.entries.clone();
+        } else {
+            let hits = search::search(&self.search_query, &self.summaries);
+            self.filtered_entries = hits.iter().map(|hit| hit.name.as_str().to_string()).collect();
+            self.highlights = hits.into_iter()
+                .filter(|hit| hit.field == MatchField::Name)
+                .map(|hit| (hit.name.as_str().to_string(), hit.positions))
+                .collect();
+        }
+        self.selected_index = 0;
+        self.refresh_rows();
+    }
+
+    /// Left collapses the selected folder (or the one holding the
+    /// selected entry), right expands it.
+    fn set_selected_folder_collapsed(&mut self, collapse: bool) {
+        let Some(row) = self.rows.get(self.selected_index) else {
+            return;
+        };
+        let folder = match row.kind {
+            TreeRowKind::Folder { .. } => row.path.clone(),
+            TreeRowKind::Entry if collapse => match row.path.rsplit_once('/') {
+                Some((parent, _)) => parent.to_string(),
+                None => return,
+            },
+            TreeRowKind::Entry => return,
+        };
+
+        if collapse {
+            self.collapsed.insert(folder.clone());
+        } else {
+            self.collapsed.remove(&folder);
+        }
+        self.refresh_rows();
+        if let Some(index) = self.rows.iter().position(|row| row.path == folder && row.kind != TreeRowKind::Entry) {
+            self.selected_index = index;
+        }
+    }
+
+    /// Open the selected entry and put its password on the clipboard;
+    /// on a folder, expand or collapse it instead.
+    async fn view_selected_entry(&mut self) -> Result<()> {
+        let Some(row) = self.rows.get(self.selected_index).cloned() else {
+            return Ok(());
+        };
+        if let TreeRowKind::Folder { collapsed, .. } = row.kind {
+            self.set_selected_folder_collapsed(!collapsed);
+            return Ok(());
+        }
+        let name = row.path;
+        let entry_name = EntryName::new(name.clone()).map_err(powda_core::Error::InvalidInput)?;
+        let entry = self.store.get(&entry_name).await?;
+
+        match (self.copy)(entry.password.as_str(), self.clear_after) {
+            Ok(()) => match self.clear_after {
+                Some(after) => self.set_message(
+                    &format!("Password copied, clearing in {}s", after.as_secs()),
+                    MessageType::Success,
+                ),
+                None => self.set_message("Password copied", MessageType::Success),
+            },
+            Err(e) => self.set_message(&format!("Failed to copy: {}", e), MessageType::Error),
+        }
+        self.viewed = Some(EntrySummary::from(&entry));
+        self.viewed_otp = entry.otp.clone();
+        self.context.state = AppState::ViewingEntry(name);
+        Ok(())
+    }
+
+    /// Current code for the entry view; the UI redraws every tick, so the
+    /// remaining seconds count down live.
+    pub fn otp_countdown(&self) -> Option<OtpCode> {
+        // Counter-based codes are only produced on request, never previewed.
+        let otp = self.viewed_otp.as_ref().filter(|otp| !otp.is_counter_based())?;
+        otp.code(SystemTime::now()).ok()
+    }
+
+    fn confirm_delete_selected(&mut self) {
+        if let Some(row) = self.rows.get(self.selected_index).filter(|row| row.kind == TreeRowKind::Entry) {
+            self.context.state = AppState::ConfirmDelete(row.path.clone());
+        }
+    }
+
+    async fn handle_confirm_delete(&mut self, key: KeyCode) -> Result<bool> {
+        let AppState::ConfirmDelete(name) = self.context.state.clone() else {
+            return Ok(false);
+        };
+        self.return_to_main();
+        if key == KeyCode::Char('y') {
+            let entry_name = EntryName::new(name.clone()).map_err(powda_core::Error::InvalidInput)?;
+            self.store.remove(&entry_name).await?;
+            self.load_entries().await?;
+            self.set_message(&format!("Deleted '{}'", name), MessageType::Success);
+        }
+        Ok(false)
+    }
+
+    fn start_add_entry(&mut self) {
+        self.return_to_main();
+        self.context.state = AppState::AddingEntry;
+        self.context.input_mode = InputMode::EntryName;
+    }
+
+    /// Add-entry name field; Enter moves on to the password.
+    fn handle_entry_name_mode(&mut self, key: KeyCode) -> Result<bool> {
+        let request = match key {
+            KeyCode::Char(c) => InputRequest::InsertChar(c),
+            KeyCode::Backspace => InputRequest::DeletePrevChar,
+            KeyCode::Delete => InputRequest::DeleteNextChar,
+            KeyCode::Left => InputRequest::GoToPrevChar,
+            KeyCode::Right => InputRequest::GoToNextChar,
+            KeyCode::Home => InputRequest::GoToStart,
+            KeyCode::End => InputRequest::GoToEnd,
+            KeyCode::Enter => {
+                match EntryName::new(self.input.value().trim().to_string()) {
+                    Ok(_) => self.context.input_mode = InputMode::EntryPassword,
+                    Err(e) => self.set_message(&e, MessageType::Error),
+                }
+                return Ok(false);
+            }
+            KeyCode::Esc => {
+                self.return_to_main();
+                return Ok(false);
+            }
+            _ => return Ok(false),
+        };
+        self.input.handle(request);
+        Ok(false)
+    }
+
+    /// Add-entry password field; Ctrl-G fills it with a generated password.
+    async fn handle_entry_password_mode(&mut self, key: KeyEvent) -> Result<bool> {
+        match key.code {
+            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => self.generate_password(),
+            KeyCode::Char(c) => self.password_input.push(c),
+            KeyCode::Backspace => { self.password_input.pop(); }
+            KeyCode::Tab => self.show_password = !self.show_password,
+            KeyCode::Enter => self.save_new_entry().await?,
+            KeyCode::Esc => self.return_to_main(),
+            _ => {}
+        }
+
+        Ok(false)
+    }
+
+    fn generate_password(&mut self) {
+        match self.recipe.generate() {
+            Ok(password) => {
+                self.password_input = password.as_str().to_string();
+                self.show_password = true;
+                self.set_message("Generated a password - Enter to save", MessageType::Info);
+            }
+            Err(e) => self.set_message(&format!("Failed to generate: {}", e), MessageType::Error),
+        }
+    }
+
+    async fn save_new_entry(&mut self) -> Result<()> {
+        let name = EntryName::new(self.input.value().trim().to_string()).map_err(powda_core::Error::InvalidInput)?;
+        let password = match Password::new(std::mem::take(&mut self.password_input)) {
+            Ok(password) => password,
+            Err(e) => {
+                self.set_message(&e, MessageType::Error);
+                return Ok(());
+            }
+        };
+        self.store.add(PasswordEntry::new(name.clone(), password)).await?;
+        self.return_to_main();
+        self.load_entries().await?;
+        if let Some(index) = self.rows.iter().position(|row| row.path == name.as_str()) {
+            self.selected_index = index;
+        }
+        self.set_message(&format!("Added '{}'", name.as_str()), MessageType::Success);
+        Ok(())
+    }
+
+    async fn handle_master_password_mode(&mut self, key: KeyCode) -> Result<bool> {
+        match key {
+            KeyCode::Char(c) => self.password_input.push(c),
+            KeyCode::Backspace => { self.password_input.pop(); }
+            KeyCode::Enter => {
+                let password = Zeroizing::new(std::mem::take(&mut self.password_input));
+                self.unlock(&password).await?;
+            }
+            KeyCode::Esc => return Ok(true),
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// A wrong password is reported and leaves the vault locked.
     async fn unlock(&mut self, password: &str) -> Result<()> {
         match self.store.unlock(password).await {
             Ok(_) => {
                 self.load_entries().await?;
+                self.session = Some(SessionClock::start());
                 self.context.state = AppState::Main;
                 self.context.input_mode = InputMode::Normal;
                 self.set_message("Vault unlocked!", MessageType::Success);
-                Ok(())
-            }
-            Err(e) => {
-                self.set_message(&format!("Failed to unlock: {}", e), MessageType::Error);
-                Err(TuiError::Core(e))
             }
+            Err(e) => self.set_message(&format!("Failed to unlock: {}", e), MessageType::Error),
         }
+        Ok(())
     }
 
     async fn load_entries(&mut self) -> Result<()> {
-        match self.store.list().await {
-            Ok(entries) => {
-                self.entries = entries.into_iter()
-                    .map(|e| e.as_str().to_string())
-                    .collect();
-                self.entries.sort();
-                self.finltered_entries = self.entries.clone();
-                Ok(())
-            }
-            Err(e) => Err(TuiError::Core(e))
-        }
+        let summaries = self.store.query(&EntryQuery::default()).await?;
+        self.entries = summaries.iter()
+            .map(|e| e.name.as_str().to_string())
+            .collect();
+        self.summaries = summaries;
+        self.update_search();
+        Ok(())
+    }
+
+    /// Drop everything decrypted and return to the unlock screen.
+    async fn auto_lock(&mut self) -> Result<()> {
+        self.store.lock().await?;
+        self.session = None;
+        self.entries.clear();
+        self.summaries.clear();
+        self.filtered_entries.clear();
+        self.highlights.clear();
+        self.search_query.clear();
+        self.rows.clear();
+        self.selected_index = 0;
+        self.show_password = false;
+        self.viewed = None;
+        self.viewed_otp = None;
+        self.password_input.zeroize();
+        self.input.reset();
+        self.context.state = AppState::Locked;
+        self.context.input_mode = InputMode::MasterPassword;
+        self.set_message("Vault locked after inactivity", MessageType::Warning);
+        Ok(())
     }
 
     fn set_message(&mut self, msg: &str, msg_type: MessageType) {
@@ -137,4 +520,252 @@ impl App {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use powda_core::{KdfParams, Store};
+    use ratatui::backend::TestBackend;
+    use ratatui::buffer::Buffer;
+    use ratatui::style::Modifier;
+    use std::path::PathBuf;
+
+    struct TestVault {
+        app: App,
+        dir: PathBuf,
+    }
+
+    impl Drop for TestVault {
+        fn drop(&mut self) {
+            std::fs::remove_dir_all(&self.dir).ok();
+        }
+    }
+
+    /// An app over a fresh vault holding `names`, not yet unlocked.
+    async fn test_vault(test: &str, names: &[&str], policy: SessionPolicy) -> TestVault {
+        let dir = std::env::temp_dir().join(format!("powda-tui-{}-{}", test, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = Store::with_path(dir.join("vault"));
+        store.init("master password", KdfParams::new(8, 1, 1).unwrap()).await.unwrap();
+        for name in names {
+            let entry = PasswordEntry::new(EntryName::new(name.to_string()).unwrap(), Password::new(format!("{}-pw", name)).unwrap());
+            store.add(entry).await.unwrap();
+        }
+        store.lock().await.unwrap();
+
+        let app = App::new(Box::new(Store::with_path(dir.join("vault"))), policy, Recipe::default(), None).await.unwrap();
+        TestVault { app, dir }
+    }
+
+    async fn press(app: &mut App, code: KeyCode) -> bool {
+        press_with(app, code, KeyModifiers::NONE).await
+    }
+
+    async fn press_with(app: &mut App, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        app.handle_event(Event::Key(KeyEvent::new(code, modifiers))).await.unwrap()
+    }
+
+    async fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c)).await;
+        }
+    }
+
+    fn draw(app: &App) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| app.ui.render(frame, app)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    fn text(buffer: &Buffer) -> String {
+        buffer.content().chunks(buffer.area.width as usize)
+            .map(|line| line.iter().map(|cell| cell.symbol()).collect::<String>() + "\n")
+            .collect()
+    }
+
+    async fn unlock(app: &mut App) {
+        type_text(app, "master password").await;
+        press(app, KeyCode::Enter).await;
+        assert_eq!(app.context().state, AppState::Main);
+    }
+
+    #[tokio::test]
+    async fn idle_sessions_lock_and_keys_keep_them_open() {
+        let policy = SessionPolicy::new(Some(Duration::from_millis(300)), None);
+        let mut vault = test_vault("idle", &["github", "gitlab"], policy).await;
+        let app = &mut vault.app;
+
+        type_text(app, "wrong").await;
+        press(app, KeyCode::Enter).await;
+        assert_eq!(app.context().state, AppState::Locked);
+        assert!(app.password_display().is_empty());
+        unlock(app).await;
+        assert_eq!(app.rows().len(), 2);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        press(app, KeyCode::Down).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        app.tick().await.unwrap();
+        assert_eq!(app.context().state, AppState::Main);
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        app.tick().await.unwrap();
+        assert_eq!(app.context().state, AppState::Locked);
+        assert_eq!(app.context().input_mode, InputMode::MasterPassword);
+        assert!(app.rows().is_empty());
+        assert!(app.store.is_locked().await);
+    }
+
+    #[tokio::test]
+    async fn ctrl_g_fills_the_add_form_with_a_generated_password() {
+        let mut vault = test_vault("generate", &["github"], SessionPolicy::new(None, None)).await;
+        let app = &mut vault.app;
+        unlock(app).await;
+
+        press(app, KeyCode::Char('a')).await;
+        assert_eq!(app.context().state, AppState::AddingEntry);
+        type_text(app, "work/vpn").await;
+        press(app, KeyCode::Enter).await;
+        assert_eq!(app.context().input_mode, InputMode::EntryPassword);
+
+        press_with(app, KeyCode::Char('g'), KeyModifiers::CONTROL).await;
+        let generated = app.password_display();
+        assert_eq!(generated.chars().count(), Recipe::default().generate().unwrap().as_str().chars().count());
+        assert!(app.show_password, "a generated password is shown so it can be noted");
+
+        press(app, KeyCode::Enter).await;
+        assert_eq!(app.context().state, AppState::Main);
+        let name = EntryName::new("work/vpn".to_string()).unwrap();
+        assert_eq!(app.store.get(&name).await.unwrap().password.as_str(), generated);
+        assert_eq!(app.rows()[app.selected_index()].path, "work/vpn");
+        assert!(app.password_display().is_empty());
+    }
+
+    static COPIED: std::sync::Mutex<Vec<(String, Option<Duration>)>> = std::sync::Mutex::new(Vec::new());
+
+    fn record_copy(secret: &str, clear_after: Option<Duration>) -> powda_core::Result<()> {
+        COPIED.lock().unwrap().push((secret.to_string(), clear_after));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn enter_opens_the_entry_and_copies_its_password() {
+        let mut vault = test_vault("copy", &["web/github"], SessionPolicy::new(None, None)).await;
+        let app = &mut vault.app;
+        app.copy = record_copy;
+        app.clear_after = Some(Duration::from_secs(45));
+        unlock(app).await;
+
+        // The folder row comes first; the entry is below it.
+        press(app, KeyCode::Down).await;
+        press(app, KeyCode::Enter).await;
+        assert_eq!(app.context().state, AppState::ViewingEntry("web/github".to_string()));
+        assert_eq!(*COPIED.lock().unwrap(), [("web/github-pw".to_string(), Some(Duration::from_secs(45)))]);
+        let (message, _) = app.context().message.as_ref().unwrap();
+        assert_eq!(message, "Password copied, clearing in 45s");
+
+        press(app, KeyCode::Esc).await;
+        assert_eq!(app.context().state, AppState::Main);
+        assert!(app.viewed().is_none());
+    }
+
+    #[tokio::test]
+    async fn viewed_totp_entries_show_a_live_code() {
+        let mut vault = test_vault("otp", &["bank", "counter"], SessionPolicy::new(None, None)).await;
+        let app = &mut vault.app;
+        unlock(app).await;
+        for (name, uri) in [
+            ("bank", "otpauth://totp/Bank?secret=JBSWY3DPEHPK3PXP"),
+            ("counter", "otpauth://hotp/Counter?secret=JBSWY3DPEHPK3PXP&counter=3"),
+        ] {
+            let mut entry = app.store.get(&EntryName::new(name.to_string()).unwrap()).await.unwrap();
+            entry.otp = Some(OtpSecret::parse(uri).unwrap());
+            app.store.update(entry).await.unwrap();
+        }
+        app.copy = |_, _| Ok(());
+
+        press(app, KeyCode::Enter).await;
+        assert_eq!(app.context().state, AppState::ViewingEntry("bank".to_string()));
+        let code = app.otp_countdown().unwrap();
+        let remaining = code.remaining.unwrap();
+        assert!(remaining <= Duration::from_secs(30) && !remaining.is_zero());
+        assert_eq!(code.code.len(), 6);
+        let screen = text(&draw(app));
+        assert!(screen.contains(&code.code) && screen.contains("s left"), "{}", screen);
+
+        // HOTP codes move the counter, so the view never shows one.
+        press(app, KeyCode::Esc).await;
+        press(app, KeyCode::Down).await;
+        press(app, KeyCode::Enter).await;
+        assert_eq!(app.context().state, AppState::ViewingEntry("counter".to_string()));
+        assert!(app.otp_countdown().is_none());
+        assert!(!text(&draw(app)).contains("Code"));
+    }
 
+    #[tokio::test]
+    async fn folders_collapse_and_expand_in_the_list() {
+        let names = ["personal", "work/aws/dev", "work/aws/prod", "work/gh"];
+        let mut vault = test_vault("tree", &names, SessionPolicy::new(None, None)).await;
+        let app = &mut vault.app;
+        unlock(app).await;
+        let paths = |app: &App| app.rows().iter().map(|row| row.path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths(app), ["work", "work/aws", "work/aws/dev", "work/aws/prod", "work/gh", "personal"]);
+
+        press(app, KeyCode::Down).await;
+        press(app, KeyCode::Left).await;
+        assert_eq!(paths(app), ["work", "work/aws", "work/gh", "personal"]);
+        assert_eq!(app.selected_index(), 1);
+        assert!(text(&draw(app)).contains("  ▸ aws/ (2)"));
+
+        press(app, KeyCode::Enter).await;
+        assert_eq!(paths(app).len(), 6);
+
+        // Left on an entry folds the folder holding it and selects that.
+        press(app, KeyCode::Down).await;
+        press(app, KeyCode::Down).await;
+        assert_eq!(app.rows()[app.selected_index()].path, "work/aws/prod");
+        press(app, KeyCode::Char('h')).await;
+        assert_eq!(paths(app), ["work", "work/aws", "work/gh", "personal"]);
+        assert_eq!(app.selected_index(), 1);
+
+        press(app, KeyCode::Up).await;
+        press(app, KeyCode::Left).await;
+        assert_eq!(paths(app), ["work", "personal"]);
+        press(app, KeyCode::Right).await;
+        assert_eq!(paths(app), ["work", "work/aws", "work/gh", "personal"]);
+    }
+
+    #[tokio::test]
+    async fn slash_search_ranks_matches_and_highlights_them() {
+        let names = ["web/github", "web/gitlab", "mail", "work/lighthouse"];
+        let mut vault = test_vault("search", &names, SessionPolicy::new(None, None)).await;
+        let app = &mut vault.app;
+        unlock(app).await;
+
+        press(app, KeyCode::Char('/')).await;
+        assert_eq!(app.context().input_mode, InputMode::Search);
+        type_text(app, "gith").await;
+        let paths: Vec<&str> = app.rows().iter().map(|row| row.path.as_str()).collect();
+        assert_eq!(paths[0], "web/github");
+        assert!(!paths.contains(&"mail") && !paths.contains(&"web/gitlab"));
+        assert_eq!(app.highlight("web/github"), [4, 5, 6, 7]);
+
+        let buffer = draw(app);
+        assert!(text(&buffer).contains("┌ /gith "));
+        let bold: String = (1..11).map(|x| &buffer[(x, 1)])
+            .map(|cell| if cell.modifier.contains(Modifier::BOLD) { cell.symbol() } else { "." })
+            .collect();
+        assert_eq!(bold, "....gith..");
+
+        // Enter keeps the matches for browsing, Esc in the prompt drops them.
+        press(app, KeyCode::Enter).await;
+        assert_eq!(app.context().input_mode, InputMode::Normal);
+        assert_eq!(app.search_query(), "gith");
+        press(app, KeyCode::Char('/')).await;
+        press(app, KeyCode::Backspace).await;
+        assert_eq!(app.search_query(), "git");
+        press(app, KeyCode::Esc).await;
+        assert_eq!(app.search_query(), "");
+        assert_eq!(app.rows().len(), 6);
+        assert!(app.highlight("web/github").is_empty());
+    }
+}
@@ -1,6 +1,6 @@
 use crossterm::event::{self, Event};
 use std::time::Duration;
-use crate::tui:types::Result;
+use crate::tui::types::Result;
 
 pub struct EventHandler;
 
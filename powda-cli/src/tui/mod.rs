@@ -12,29 +12,30 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use powda_core::{Recipe, SessionPolicy, Store, VaultLocation};
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
+use std::time::Duration;
 
-pub async fn run() -> Result<()> {
-    let mut terminal = setup_terminal()?;
-    let app = App::new().await?;
+/// Browse `vault` full screen. The session locks itself under `policy`;
+/// Ctrl-G in the add form fills in a password from `recipe`.
+pub async fn run(vault: &VaultLocation, policy: SessionPolicy, recipe: Recipe, clear_after: Option<Duration>) -> powda_core::Result<()> {
+    let store = Box::new(Store::with_path(vault.path.clone()).with_session_policy(policy));
+    let app = App::new(store, policy, recipe, clear_after).await?;
 
+    let mut terminal = setup_terminal()?;
     let res = app.run(&mut terminal).await;
+    restore_terminal(&mut terminal)?;
 
-    restore_terminal(&mut terminal).await;
-
-    if let Err(err) = res {
-        eprintln!("Error: {?}", err);
-    }
-    Ok(())
+    Ok(res?)
 }
 
 fn setup_terminal() -> Result<Terminal<CrosstermBackend<io::Stdout>>> {
     enable_raw_mode()?;
-    let mut stdout = ui::stdout();
+    let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
-    let terminal - Terminal::new(backend)?;
+    let terminal = Terminal::new(backend)?;
     Ok(terminal)
 }
 
@@ -47,4 +48,4 @@ fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Re
     )?;
     terminal.show_cursor()?;
     Ok(())
-
+}
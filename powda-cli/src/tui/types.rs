@@ -10,13 +10,20 @@ pub enum TuiError {
     #[error("Core error: {0}")]
     Core(#[from] powda_core::Error),
 
-    #[error("Terminal error: {0}")]
-    Terminal(String),
-
     #[error("No vault found. Please run 'powda init' first")]
     NoVault,
 }
 
+impl From<TuiError> for powda_core::Error {
+    fn from(e: TuiError) -> Self {
+        match e {
+            TuiError::Io(e) => powda_core::Error::Io(e),
+            TuiError::Core(e) => e,
+            TuiError::NoVault => powda_core::Error::NotInitialized,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppState {
     Locked,
@@ -28,7 +35,7 @@ pub enum AppState {
     ConfirmDelete(String),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputMode {
     Normal,
     EntryName,
@@ -39,10 +46,9 @@ pub enum InputMode {
 
 #[derive(Debug)]
 pub struct AppContext {
-    Success,
-    Error,
-    Info,
-    Warning,
+    pub state: AppState,
+    pub input_mode: InputMode,
+    pub message: Option<(String, MessageType)>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -60,7 +66,7 @@ impl MessageType {
             MessageType::Success => Color::Green,
             MessageType::Error => Color::Red,
             MessageType::Info => Color::Cyan,
-            MessageType::Warning => COlor::Yellow,
+            MessageType::Warning => Color::Yellow,
         }
     }
 }
@@ -0,0 +1,164 @@
+use crate::tui::{
+    app::App,
+    types::{AppState, InputMode},
+    widgets::highlighted,
+};
+use powda_core::domain::TreeRowKind;
+use ratatui::{
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+const HELP: &[(&str, &str)] = &[
+    ("Enter", "open the entry and copy its password, or open/close a folder"),
+    ("Left / Right", "collapse / expand the folder"),
+    ("Up / Down", "move"),
+    ("/", "search; Enter keeps the matches, Esc clears them"),
+    ("a", "add an entry; Ctrl-G generates its password, Tab shows it"),
+    ("d", "delete the entry"),
+    ("q", "quit"),
+];
+
+pub struct UI;
+
+impl UI {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn render(&self, frame: &mut Frame, app: &App) {
+        let [body, status, keys] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ]).areas(frame.area());
+
+        match app.context().state {
+            AppState::Locked => self.render_locked(frame, app, body),
+            _ => {
+                let [list, detail] = Layout::horizontal([
+                    Constraint::Percentage(40),
+                    Constraint::Percentage(60),
+                ]).areas(body);
+                self.render_list(frame, app, list);
+                self.render_detail(frame, app, detail);
+            }
+        }
+
+        if let Some((message, kind)) = &app.context().message {
+            frame.render_widget(Paragraph::new(message.as_str()).style(Style::default().fg(kind.color())), status);
+        }
+        frame.render_widget(Paragraph::new(self.key_hint(app)).style(Style::default().fg(Color::DarkGray)), keys);
+    }
+
+    fn render_locked(&self, frame: &mut Frame, app: &App, area: Rect) {
+        let prompt = format!("Master password: {}", app.password_display());
+        let text = vec![Line::from("The vault is locked."), Line::from(""), Line::from(prompt.clone())];
+        frame.render_widget(Paragraph::new(text).block(Block::default().title(" powda ").borders(Borders::ALL)), area);
+        frame.set_cursor_position(Position::new(area.x + 1 + prompt.chars().count() as u16, area.y + 3));
+    }
+
+    fn render_list(&self, frame: &mut Frame, app: &App, area: Rect) {
+        let matched = Style::default().fg(Color::Yellow);
+        let items: Vec<ListItem> = app.rows().iter().map(|row| {
+            let indent = Span::raw("  ".repeat(row.depth));
+            let line = match row.kind {
+                TreeRowKind::Folder { entries, collapsed } => {
+                    let marker = if collapsed { "▸" } else { "▾" };
+                    Line::from(vec![
+                        indent,
+                        Span::styled(format!("{} {}/", marker, row.label), Style::default().fg(Color::Blue)),
+                        Span::styled(format!(" ({})", entries), Style::default().fg(Color::DarkGray)),
+                    ])
+                }
+                TreeRowKind::Entry => {
+                    let mut line = highlighted(&row.label, app.highlight(&row.path), matched);
+                    line.spans.insert(0, indent);
+                    line
+                }
+            };
+            ListItem::new(line)
+        }).collect();
+
+        let title = match app.search_query() {
+            "" => " Entries ".to_string(),
+            query => format!(" /{} ", query),
+        };
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected((!app.rows().is_empty()).then_some(app.selected_index()));
+        frame.render_stateful_widget(list, area, &mut state);
+
+        if app.context().input_mode == InputMode::Search {
+            frame.set_cursor_position(Position::new(area.x + 2 + app.search_query().chars().count() as u16, area.y));
+        }
+    }
+
+    fn render_detail(&self, frame: &mut Frame, app: &App, area: Rect) {
+        let block = Block::default().borders(Borders::ALL);
+        let (title, lines): (&str, Vec<Line>) = match &app.context().state {
+            AppState::ViewingEntry(name) => (" Entry ", self.entry_lines(app, name)),
+            AppState::AddingEntry => {
+                let name = format!("Name:     {}", app.entry_name_input().value());
+                let password = format!("Password: {}", app.password_display());
+                let (x, y) = match app.context().input_mode {
+                    InputMode::EntryName => (10 + app.entry_name_input().visual_cursor(), 0),
+                    _ => (password.chars().count(), 1),
+                };
+                frame.set_cursor_position(Position::new(area.x + 1 + x as u16, area.y + 1 + y));
+                (" New entry ", vec![Line::from(name), Line::from(password)])
+            }
+            AppState::ConfirmDelete(name) => (" Delete ", vec![Line::from(format!("Delete '{}'? (y/N)", name))]),
+            AppState::Help => (" Keys ", HELP.iter()
+                .map(|(key, action)| Line::from(vec![
+                    Span::styled(format!("{:<14}", key), Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(*action),
+                ]))
+                .collect()),
+            _ => (" powda ", vec![Line::from("Enter opens an entry, ? lists the keys.")]),
+        };
+        frame.render_widget(Paragraph::new(lines).block(block.title(title)), area);
+    }
+
+    fn entry_lines(&self, app: &App, name: &str) -> Vec<Line<'static>> {
+        let label = |text: &str| Span::styled(format!("{:<10}", text), Style::default().add_modifier(Modifier::BOLD));
+        let mut lines = vec![Line::from(vec![label("Name"), Span::raw(name.to_string())])];
+        if let Some(entry) = app.viewed() {
+            if let Some(username) = &entry.username {
+                lines.push(Line::from(vec![label("Username"), Span::raw(username.clone())]));
+            }
+            if let Some(url) = &entry.url {
+                lines.push(Line::from(vec![label("URL"), Span::raw(url.clone())]));
+            }
+            if !entry.tags.is_empty() {
+                lines.push(Line::from(vec![label("Tags"), Span::raw(entry.tags.join(", "))]));
+            }
+        }
+        lines.push(Line::from(vec![label("Password"), Span::raw("******** (Enter copies it again)")]));
+        if let Some(otp) = app.otp_countdown() {
+            let remaining = otp.remaining.map(|left| format!("  {}s left", left.as_secs())).unwrap_or_default();
+            lines.push(Line::from(vec![
+                label("Code"),
+                Span::styled(otp.code, Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+                Span::raw(remaining),
+            ]));
+        }
+        lines
+    }
+
+    fn key_hint(&self, app: &App) -> &'static str {
+        match (&app.context().state, app.context().input_mode) {
+            (AppState::Locked, _) => "Enter unlock  Esc quit",
+            (_, InputMode::Search) => "type to search  Enter keep matches  Esc clear",
+            (_, InputMode::EntryName) => "Enter next  Esc cancel",
+            (_, InputMode::EntryPassword) => "Enter save  Ctrl-G generate  Tab show/hide  Esc cancel",
+            (AppState::ConfirmDelete(_), _) => "y delete  any other key cancels",
+            (AppState::Help, _) => "any key closes the help",
+            _ => "Enter open/copy  / search  a add  d delete  ? help  q quit",
+        }
+    }
+}
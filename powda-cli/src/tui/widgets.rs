@@ -0,0 +1,16 @@
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+
+/// `label` with the characters at `positions` (character indices, as
+/// returned by the fuzzy matcher) emphasised.
+pub fn highlighted(label: &str, positions: &[usize], matched: Style) -> Line<'static> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    for (i, c) in label.chars().enumerate() {
+        let style = if positions.contains(&i) { matched.add_modifier(Modifier::BOLD) } else { Style::default() };
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push(c),
+            _ => spans.push(Span::styled(c.to_string(), style)),
+        }
+    }
+    Line::from(spans)
+}
@@ -1,8 +1,44 @@
 
-use rpassword;
-use powda_core::{Result, Error};
+use powda_core::{timestamp, Result, Error};
+use std::io::IsTerminal;
+use std::time::SystemTime;
 
 pub fn prompt_password(prompt: &str) -> Result<String> {
     rpassword::prompt_password(prompt)
-            .map_err(|e| Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))
+            .map_err(|e| Error::Io(std::io::Error::other(e)))
+}
+
+/// Ask a yes/no question on stderr; anything but "y" means no.
+pub fn confirm(question: &str) -> Result<bool> {
+    eprintln!("{} (y/N)", question);
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
+
+/// Ask on stderr which of `options` to use. `None` means the user gave
+/// up, or stdin is not a terminal so nobody could answer.
+pub fn choose(question: &str, options: &[String]) -> Result<Option<usize>> {
+    if !std::io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    eprintln!("{}", question);
+    for (i, option) in options.iter().enumerate() {
+        eprintln!("  {}) {}", i + 1, option);
+    }
+    eprint!("Pick one [1-{}]: ", options.len());
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim().parse::<usize>().ok().filter(|n| (1..=options.len()).contains(n)).map(|n| n - 1))
+}
+
+/// Format a timestamp as `YYYY-MM-DD HH:MM:SS UTC`.
+pub fn format_time(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = timestamp::civil(time);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, hour, minute, second)
+}
+
+/// Format a timestamp as RFC 3339 (`YYYY-MM-DDTHH:MM:SSZ`) for JSON output.
+pub fn format_rfc3339(time: SystemTime) -> String {
+    timestamp::format_rfc3339(time)
 }
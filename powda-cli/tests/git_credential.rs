@@ -0,0 +1,106 @@
+//! `powda git-credential` as git itself runs it: git asks the helper for
+//! a login after a 401 from a local stand-in server, and sends whatever
+//! the helper answered.
+
+use powda_core::agent::socket_path;
+use powda_core::{AgentClient, EntryMetadata, EntryName, KdfParams, Password, PasswordEntry, Store, StoreRepository};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+const POWDA: &str = env!("CARGO_BIN_EXE_powda");
+
+/// `powda agent` for the test vault, stopped even if the test fails.
+struct Agent(Child);
+
+impl Drop for Agent {
+    fn drop(&mut self) {
+        self.0.kill().ok();
+        self.0.wait().ok();
+    }
+}
+
+fn powda(home: &Path) -> Command {
+    let mut command = Command::new(POWDA);
+    command.env("HOME", home).env("XDG_CONFIG_HOME", home.join("config"));
+    command
+}
+
+/// Answer HTTP requests with 401 until one carries credentials, and
+/// return its `Authorization` header.
+fn demand_basic_auth(listener: TcpListener) -> Option<String> {
+    for stream in listener.incoming().take(5) {
+        let mut stream = stream.ok()?;
+        let mut authorization = None;
+        for line in BufReader::new(stream.try_clone().ok()?).lines() {
+            let line = line.ok()?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':')
+                && name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+        if authorization.is_some() {
+            stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").ok()?;
+            return authorization;
+        }
+        stream.write_all(
+            b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"powda\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ).ok()?;
+    }
+    None
+}
+
+#[tokio::test]
+async fn git_sends_the_login_the_helper_found() {
+    if Command::new("git").arg("--version").output().is_err() {
+        eprintln!("git is not installed; skipping");
+        return;
+    }
+    let home = std::env::temp_dir().join(format!("powda-git-credential-{}", std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+    let vault = home.join("vault");
+    Store::with_path(vault.clone()).init("master password", KdfParams::new(8, 1, 1).unwrap()).await.unwrap();
+
+    let _agent = Agent(powda(&home).arg("--vault").arg(&vault).arg("agent")
+        .stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null())
+        .spawn().unwrap());
+    let socket = socket_path(&vault).unwrap();
+    let client = (0..50).find_map(|_| {
+        std::thread::sleep(Duration::from_millis(50));
+        AgentClient::connect(socket.clone())
+    }).expect("the agent did not start");
+    client.unlock("master password").await.unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (sent, authorization) = std::sync::mpsc::channel();
+    std::thread::spawn(move || sent.send(demand_basic_auth(listener)));
+    let metadata = EntryMetadata { url: Some(url.clone()), username: Some("dev".to_string()), ..Default::default() };
+    let entry = PasswordEntry::new(EntryName::new("git/local".to_string()).unwrap(), Password::new("s3cret".to_string()).unwrap());
+    client.add(entry.with_metadata(metadata)).await.unwrap();
+
+    // An empty helper first clears any helper from the user's own config.
+    let helper = format!("credential.helper=!'{}' --vault '{}' git-credential", POWDA, vault.display());
+    let git = Command::new("git")
+        .args(["-c", "credential.helper=", "-c", &helper, "ls-remote", &format!("{}/repo.git", url)])
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env_remove("GIT_ASKPASS")
+        .env_remove("SSH_ASKPASS")
+        .output()
+        .unwrap();
+
+    // base64 of "dev:s3cret"
+    let authorization = authorization.recv_timeout(Duration::from_secs(5)).ok().flatten();
+    assert_eq!(authorization.as_deref(), Some("Basic ZGV2OnMzY3JldA=="), "git said: {}", String::from_utf8_lossy(&git.stderr));
+
+    client.shutdown().unwrap();
+    std::fs::remove_dir_all(&home).unwrap();
+}
@@ -0,0 +1,62 @@
+//! `powda --format json remove` as a script runs it, with nobody on stdin
+//! to answer the confirmation.
+
+use powda_core::agent::socket_path;
+use powda_core::{AgentClient, EntryName, KdfParams, Password, PasswordEntry, Store, StoreRepository};
+use serde_json::Value;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+/// `powda agent` for the test vault, stopped even if the test fails.
+struct Agent(Child);
+
+impl Drop for Agent {
+    fn drop(&mut self) {
+        self.0.kill().ok();
+        self.0.wait().ok();
+    }
+}
+
+fn powda(home: &Path, vault: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_powda"));
+    command.env("HOME", home).env("XDG_CONFIG_HOME", home.join("config")).arg("--vault").arg(vault);
+    command.stdin(Stdio::null()).stderr(Stdio::null());
+    command
+}
+
+fn json(command: &mut Command) -> (bool, Value) {
+    let output = command.output().unwrap();
+    (output.status.success(), serde_json::from_slice(&output.stdout).unwrap())
+}
+
+#[tokio::test]
+async fn json_remove_needs_yes_instead_of_reporting_a_silent_no() {
+    let home = std::env::temp_dir().join(format!("powda-remove-{}", std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+    let vault = home.join("vault");
+    Store::with_path(vault.clone()).init("master password", KdfParams::new(8, 1, 1).unwrap()).await.unwrap();
+
+    let _agent = Agent(powda(&home, &vault).arg("agent").stdout(Stdio::null()).spawn().unwrap());
+    let socket = socket_path(&vault).unwrap();
+    let client = (0..50).find_map(|_| {
+        std::thread::sleep(Duration::from_millis(50));
+        AgentClient::connect(socket.clone())
+    }).expect("the agent did not start");
+    client.unlock("master password").await.unwrap();
+    let name = EntryName::new("db".to_string()).unwrap();
+    client.add(PasswordEntry::new(name.clone(), Password::new("pw".to_string()).unwrap())).await.unwrap();
+
+    let (ok, refused) = json(powda(&home, &vault).args(["--format", "json", "remove", "db"]));
+    assert!(!ok);
+    assert_eq!(refused["error"]["code"], "invalid_input");
+    assert!(client.get(&name).await.is_ok());
+
+    let (ok, removed) = json(powda(&home, &vault).args(["--format", "json", "remove", "db", "--yes"]));
+    assert!(ok);
+    assert_eq!(removed, serde_json::json!({ "name": "db", "removed": true }));
+    assert!(client.get(&name).await.is_err());
+
+    client.shutdown().unwrap();
+    std::fs::remove_dir_all(&home).unwrap();
+}
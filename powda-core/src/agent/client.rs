@@ -0,0 +1,173 @@
+use crate::crypto::KdfParams;
+use crate::domain::{EntryName, EntryQuery, EntrySummary, HistoryRetention, PasswordEntry, RetentionChange};
+use crate::error::{Error, Result};
+use crate::import::{CollisionStrategy, ImportReport};
+use crate::repository::StoreRepository;
+use super::protocol::{Request, Response};
+use async_trait::async_trait;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+/// `StoreRepository` backed by a running `powda agent`.
+pub struct AgentClient {
+    socket: PathBuf,
+}
+
+impl AgentClient {
+    /// Returns a client only if an agent is currently listening on `socket`.
+    pub fn connect(socket: PathBuf) -> Option<Self> {
+        UnixStream::connect(&socket).ok()?;
+        Some(Self { socket })
+    }
+
+    pub fn socket(&self) -> &PathBuf {
+        &self.socket
+    }
+
+    pub fn call(&self, request: &Request) -> Result<Response> {
+        let mut stream = UnixStream::connect(&self.socket)
+            .map_err(|e| Error::Agent(format!("Cannot reach agent: {}", e)))?;
+
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        stream.write_all(&line)?;
+        stream.flush()?;
+
+        let mut reply = String::new();
+        BufReader::new(&stream).read_line(&mut reply)?;
+        if reply.is_empty() {
+            return Err(Error::Agent("Agent closed the connection".to_string()));
+        }
+
+        match serde_json::from_str(&reply)? {
+            Response::Error(e) => Err(e.into()),
+            response => Ok(response),
+        }
+    }
+
+    pub fn shutdown(&self) -> Result<()> {
+        self.expect_ok(&Request::Shutdown)
+    }
+
+    fn expect_ok(&self, request: &Request) -> Result<()> {
+        match self.call(request)? {
+            Response::Ok => Ok(()),
+            _ => Err(unexpected()),
+        }
+    }
+
+    fn expect_bool(&self, request: &Request) -> Result<bool> {
+        match self.call(request)? {
+            Response::Bool(value) => Ok(value),
+            _ => Err(unexpected()),
+        }
+    }
+}
+
+fn unexpected() -> Error {
+    Error::Agent("Unexpected response from agent".to_string())
+}
+
+#[async_trait]
+impl StoreRepository for AgentClient {
+    async fn init(&self, master_password: &str, kdf: KdfParams) -> Result<()> {
+        self.expect_ok(&Request::Init { master_password: master_password.to_string(), kdf })
+    }
+
+    async fn unlock(&self, master_password: &str) -> Result<()> {
+        self.expect_ok(&Request::Unlock { master_password: master_password.to_string() })
+    }
+
+    async fn lock(&self) -> Result<()> {
+        self.expect_ok(&Request::Lock)
+    }
+
+    // An unreachable agent cannot hand out secrets, so treat it as locked.
+    async fn is_locked(&self) -> bool {
+        self.expect_bool(&Request::IsLocked).unwrap_or(true)
+    }
+
+    async fn exists(&self) -> bool {
+        self.expect_bool(&Request::Exists).unwrap_or(false)
+    }
+
+    async fn add(&self, entry: PasswordEntry) -> Result<()> {
+        self.expect_ok(&Request::Add { entry })
+    }
+
+    async fn get(&self, name: &EntryName) -> Result<PasswordEntry> {
+        match self.call(&Request::Get { name: name.clone() })? {
+            Response::Entry(entry) => Ok(*entry),
+            _ => Err(unexpected()),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<EntryName>> {
+        match self.call(&Request::List)? {
+            Response::Names(names) => Ok(names),
+            _ => Err(unexpected()),
+        }
+    }
+
+    async fn entries(&self) -> Result<Vec<PasswordEntry>> {
+        match self.call(&Request::Entries)? {
+            Response::Entries(entries) => Ok(entries),
+            _ => Err(unexpected()),
+        }
+    }
+
+    async fn query(&self, query: &EntryQuery) -> Result<Vec<EntrySummary>> {
+        match self.call(&Request::Query { query: query.clone() })? {
+            Response::Summaries(found) => Ok(found),
+            _ => Err(unexpected()),
+        }
+    }
+
+    async fn update(&self, entry: PasswordEntry) -> Result<()> {
+        self.expect_ok(&Request::Update { entry })
+    }
+
+    async fn remove(&self, name: &EntryName) -> Result<()> {
+        self.expect_ok(&Request::Remove { name: name.clone() })
+    }
+
+    async fn change_master_password(&self, current: &str, new: &str) -> Result<()> {
+        self.expect_ok(&Request::ChangeMasterPassword {
+            current: current.to_string(),
+            new: new.to_string(),
+        })
+    }
+
+    async fn upgrade_kdf(&self, master_password: &str, kdf: KdfParams) -> Result<()> {
+        self.expect_ok(&Request::UpgradeKdf { master_password: master_password.to_string(), kdf })
+    }
+
+    async fn history_retention(&self) -> Result<HistoryRetention> {
+        match self.call(&Request::HistoryRetention)? {
+            Response::Retention(retention) => Ok(retention),
+            _ => Err(unexpected()),
+        }
+    }
+
+    async fn update_history_retention(&self, change: RetentionChange) -> Result<HistoryRetention> {
+        match self.call(&Request::UpdateHistoryRetention { change })? {
+            Response::Retention(retention) => Ok(retention),
+            _ => Err(unexpected()),
+        }
+    }
+
+    async fn rename(&self, from: &EntryName, to: &EntryName) -> Result<Vec<(EntryName, EntryName)>> {
+        match self.call(&Request::Rename { from: from.clone(), to: to.clone() })? {
+            Response::Renamed(moved) => Ok(moved),
+            _ => Err(unexpected()),
+        }
+    }
+
+    async fn import(&self, entries: Vec<PasswordEntry>, strategy: CollisionStrategy) -> Result<ImportReport> {
+        match self.call(&Request::Import { entries, strategy })? {
+            Response::Imported(report) => Ok(report),
+            _ => Err(unexpected()),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+mod client;
+mod protocol;
+mod server;
+
+pub use client::AgentClient;
+pub use protocol::{Request, Response, RemoteError};
+pub use server::handle_request;
+
+use crate::error::{Error, Result};
+use std::fs::DirBuilder;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use sha2::{Digest, Sha256};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// Directory holding the agent socket. Created with 0700 so only the
+/// owning user can reach the socket inside it.
+pub fn socket_dir() -> Result<PathBuf> {
+    let uid = unsafe { libc::getuid() };
+
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime) if !runtime.is_empty() => PathBuf::from(runtime).join("powda"),
+        _ => std::env::temp_dir().join(format!("powda-{}", uid)),
+    };
+
+    DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+
+    let meta = std::fs::metadata(&dir)?;
+    if meta.uid() != uid {
+        return Err(Error::Agent(format!("{:?} is owned by another user", dir)));
+    }
+    if meta.permissions().mode() & 0o077 != 0 {
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+
+    Ok(dir)
+}
+
+/// Socket of the agent serving `vault`. Each vault gets its own agent;
+/// the name is derived from the absolute path so a vault reached by name
+/// and by path shares one.
+pub fn socket_path(vault: &Path) -> Result<PathBuf> {
+    Ok(socket_dir()?.join(format!("agent-{}.sock", vault_id(vault)?)))
+}
+
+/// Default socket of `powda ssh-agent` for `vault`.
+pub fn ssh_socket_path(vault: &Path) -> Result<PathBuf> {
+    Ok(socket_dir()?.join(format!("ssh-{}.sock", vault_id(vault)?)))
+}
+
+fn vault_id(vault: &Path) -> Result<String> {
+    let absolute = std::path::absolute(vault)?;
+    let digest = Sha256::digest(absolute.as_os_str().as_bytes());
+    Ok(digest[..8].iter().map(|b| format!("{:02x}", b)).collect())
+}
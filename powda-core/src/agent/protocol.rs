@@ -0,0 +1,125 @@
+use crate::crypto::KdfParams;
+use crate::domain::{EntryName, EntryQuery, EntrySummary, HistoryRetention, PasswordEntry, RetentionChange};
+use crate::error::Error;
+use crate::import::{CollisionStrategy, ImportReport};
+use serde::{Deserialize, Serialize};
+
+/// One line of JSON sent from the client to the agent.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Request {
+    Init { master_password: String, kdf: KdfParams },
+    Unlock { master_password: String },
+    Lock,
+    IsLocked,
+    Exists,
+    Add { entry: PasswordEntry },
+    Get { name: EntryName },
+    List,
+    Entries,
+    Query { query: EntryQuery },
+    Update { entry: PasswordEntry },
+    Remove { name: EntryName },
+    Rename { from: EntryName, to: EntryName },
+    ChangeMasterPassword { current: String, new: String },
+    UpgradeKdf { master_password: String, kdf: KdfParams },
+    HistoryRetention,
+    UpdateHistoryRetention { change: RetentionChange },
+    Import { entries: Vec<PasswordEntry>, strategy: CollisionStrategy },
+    Shutdown,
+}
+
+/// One line of JSON sent back by the agent for every request.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "status", content = "value", rename_all = "snake_case")]
+pub enum Response {
+    Ok,
+    Bool(bool),
+    Entry(Box<PasswordEntry>),
+    Names(Vec<EntryName>),
+    Entries(Vec<PasswordEntry>),
+    Summaries(Vec<EntrySummary>),
+    Retention(HistoryRetention),
+    Imported(ImportReport),
+    Renamed(Vec<(EntryName, EntryName)>),
+    Error(RemoteError),
+}
+
+/// Wire form of `Error`, one variant each so `code()` is the same through
+/// the agent; io and serde errors are flattened to text.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum RemoteError {
+    NotInitialized,
+    AlreadyExists(String),
+    NotFound(String),
+    Io(String),
+    Serialization(String),
+    Encryption(String),
+    Agent(String),
+    HeaderTampered,
+    Conflict(String),
+    InvalidInput(String),
+}
+
+impl From<&Error> for RemoteError {
+    fn from(e: &Error) -> Self {
+        match e {
+            Error::NotInitialized => RemoteError::NotInitialized,
+            Error::AlreadyExists(name) => RemoteError::AlreadyExists(name.clone()),
+            Error::NotFound(name) => RemoteError::NotFound(name.clone()),
+            Error::Io(e) => RemoteError::Io(e.to_string()),
+            Error::Serialization(e) => RemoteError::Serialization(e.to_string()),
+            Error::Encryption(msg) => RemoteError::Encryption(msg.clone()),
+            Error::Agent(msg) => RemoteError::Agent(msg.clone()),
+            Error::HeaderTampered => RemoteError::HeaderTampered,
+            Error::Conflict(msg) => RemoteError::Conflict(msg.clone()),
+            Error::InvalidInput(msg) => RemoteError::InvalidInput(msg.clone()),
+        }
+    }
+}
+
+impl From<RemoteError> for Error {
+    fn from(e: RemoteError) -> Self {
+        match e {
+            RemoteError::NotInitialized => Error::NotInitialized,
+            RemoteError::AlreadyExists(name) => Error::AlreadyExists(name),
+            RemoteError::NotFound(name) => Error::NotFound(name),
+            RemoteError::Io(msg) => Error::Io(std::io::Error::other(msg)),
+            RemoteError::Serialization(msg) => Error::Serialization(serde::de::Error::custom(msg)),
+            RemoteError::Encryption(msg) => Error::Encryption(msg),
+            RemoteError::Agent(msg) => Error::Agent(msg),
+            RemoteError::HeaderTampered => Error::HeaderTampered,
+            RemoteError::Conflict(msg) => Error::Conflict(msg),
+            RemoteError::InvalidInput(msg) => Error::InvalidInput(msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_keep_their_code_and_message_over_the_wire() {
+        let errors = [
+            Error::NotInitialized,
+            Error::AlreadyExists("web/github".to_string()),
+            Error::NotFound("web/github".to_string()),
+            Error::Io(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "vault.enc")),
+            Error::Serialization(serde_json::from_str::<EntryName>("{").unwrap_err()),
+            Error::Encryption("Invalid Master password".to_string()),
+            Error::Agent("Agent did not start in time".to_string()),
+            Error::HeaderTampered,
+            Error::Conflict("entry changed".to_string()),
+            Error::InvalidInput("empty name".to_string()),
+        ];
+        for error in errors {
+            let line = serde_json::to_string(&Response::Error(RemoteError::from(&error))).unwrap();
+            let Response::Error(remote) = serde_json::from_str(&line).unwrap() else { panic!("{}", line) };
+            let back = Error::from(remote);
+            assert_eq!(back.code(), error.code());
+            assert_eq!(back.to_string(), error.to_string());
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::repository::StoreRepository;
+use super::protocol::{Request, Response};
+
+/// Runs a single request against the store the agent is holding.
+/// Socket handling is left to the caller so the core stays runtime-agnostic.
+pub async fn handle_request(store: &dyn StoreRepository, request: Request) -> Response {
+    let result = match request {
+        Request::Init { master_password, kdf } => store.init(&master_password, kdf).await.map(|_| Response::Ok),
+        Request::Unlock { master_password } => store.unlock(&master_password).await.map(|_| Response::Ok),
+        Request::Lock => store.lock().await.map(|_| Response::Ok),
+        Request::IsLocked => Ok(Response::Bool(store.is_locked().await)),
+        Request::Exists => Ok(Response::Bool(store.exists().await)),
+        Request::Add { entry } => store.add(entry).await.map(|_| Response::Ok),
+        Request::Get { name } => store.get(&name).await.map(|entry| Response::Entry(Box::new(entry))),
+        Request::List => store.list().await.map(Response::Names),
+        Request::Entries => store.entries().await.map(Response::Entries),
+        Request::Query { query } => store.query(&query).await.map(Response::Summaries),
+        Request::Update { entry } => store.update(entry).await.map(|_| Response::Ok),
+        Request::Remove { name } => store.remove(&name).await.map(|_| Response::Ok),
+        Request::ChangeMasterPassword { current, new } => {
+            store.change_master_password(&current, &new).await.map(|_| Response::Ok)
+        }
+        Request::UpgradeKdf { master_password, kdf } => {
+            store.upgrade_kdf(&master_password, kdf).await.map(|_| Response::Ok)
+        }
+        Request::HistoryRetention => store.history_retention().await.map(Response::Retention),
+        Request::UpdateHistoryRetention { change } => {
+            store.update_history_retention(change).await.map(Response::Retention)
+        }
+        Request::Rename { from, to } => store.rename(&from, &to).await.map(Response::Renamed),
+        Request::Import { entries, strategy } => {
+            store.import(entries, strategy).await.map(Response::Imported)
+        }
+        Request::Shutdown => store.lock().await.map(|_| Response::Ok),
+    };
+
+    result.unwrap_or_else(|e| Response::Error((&e).into()))
+}
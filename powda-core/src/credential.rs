@@ -0,0 +1,196 @@
+//! git's credential helper protocol, for `powda git-credential`.
+//!
+//! git writes `key=value` lines ending with a blank line and reads the
+//! same format back; see gitcredentials(7). Entries are matched on their
+//! URL: the host (and port) must be the same, the scheme too if the entry
+//! has one, and a path on the entry must be a prefix of the requested
+//! path. The username must match when git already knows it.
+
+use crate::domain::{EntryName, EntrySummary};
+use crate::error::{Error, Result};
+
+/// What git is asking about.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CredentialRequest {
+    pub protocol: Option<String>,
+    /// Host, with `:port` if there is one.
+    pub host: Option<String>,
+    /// Only sent when git's `credential.useHttpPath` is on.
+    pub path: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl CredentialRequest {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut request = Self::default();
+        for line in input.lines() {
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| Error::InvalidInput(format!(
+                "git sent '{}', expected key=value", line
+            )))?;
+            let value = Some(value.to_string());
+            match key {
+                "protocol" => request.protocol = value,
+                "host" => request.host = value,
+                "path" => request.path = value,
+                "username" => request.username = value,
+                "password" => request.password = value,
+                "url" => {
+                    let (protocol, host, path) = split_url(value.as_deref().unwrap_or_default());
+                    request.protocol = protocol.map(str::to_string);
+                    request.host = Some(host.to_string());
+                    request.path = (!path.is_empty()).then(|| path.to_string());
+                }
+                // Newer git sends capabilities and the like; skip what we do not use.
+                _ => {}
+            }
+        }
+        Ok(request)
+    }
+
+    /// How well `url` and `username` fit this request: `None` if they do
+    /// not, otherwise higher for more specific entries.
+    pub fn score(&self, url: &str, username: Option<&str>) -> Option<usize> {
+        let host = self.host.as_deref()?;
+        let (protocol, entry_host, entry_path) = split_url(url);
+
+        if !entry_host.eq_ignore_ascii_case(host) {
+            return None;
+        }
+        if let (Some(wanted), Some(protocol)) = (self.protocol.as_deref(), protocol)
+            && !wanted.eq_ignore_ascii_case(protocol) {
+            return None;
+        }
+        if let Some(wanted) = self.username.as_deref()
+            && username != Some(wanted) {
+            return None;
+        }
+
+        let entry_path = entry_path.trim_matches('/');
+        let path = self.path.as_deref().unwrap_or_default().trim_matches('/');
+        let path_matches = entry_path.is_empty() || path == entry_path
+            || path.strip_prefix(entry_path).is_some_and(|rest| rest.starts_with('/'));
+        path_matches.then_some(1 + entry_path.len() + usize::from(protocol.is_some()))
+    }
+
+    /// The entry that fits best; ties go to the first.
+    pub fn best_match<'a>(&self, entries: &'a [EntrySummary]) -> Option<&'a EntrySummary> {
+        entries.iter()
+            .filter_map(|entry| {
+                let score = self.score(entry.url.as_deref()?, entry.username.as_deref())?;
+                Some((score, entry))
+            })
+            .rev()
+            .max_by_key(|(score, _)| *score)
+            .map(|(_, entry)| entry)
+    }
+
+    /// URL to store on a new entry for this credential.
+    pub fn url(&self) -> String {
+        let mut url = format!(
+            "{}://{}",
+            self.protocol.as_deref().unwrap_or("https"),
+            self.host.as_deref().unwrap_or_default()
+        );
+        if let Some(path) = self.path.as_deref().filter(|p| !p.is_empty()) {
+            url.push('/');
+            url.push_str(path.trim_start_matches('/'));
+        }
+        url
+    }
+
+    /// `<folder>/<host>/<username>` for a credential stored by git.
+    pub fn entry_name(&self, folder: &str) -> Result<EntryName> {
+        let host = self.host.as_deref().ok_or_else(|| Error::InvalidInput("git sent no host".to_string()))?;
+        let username = self.username.as_deref().ok_or_else(|| Error::InvalidInput("git sent no username".to_string()))?;
+        let name = format!("{}/{}/{}", folder.trim_matches('/'), host, username.replace('/', "_"));
+        EntryName::new(name).map_err(Error::InvalidInput)
+    }
+}
+
+/// The reply to `get`. A newline or NUL in a value would end it early
+/// or let it smuggle in more keys, so those are refused.
+pub fn response(username: Option<&str>, password: &str) -> Result<String> {
+    let mut out = String::new();
+    for (key, value) in [("username", username), ("password", Some(password))] {
+        let Some(value) = value else { continue };
+        if value.contains(['\n', '\0']) {
+            return Err(Error::InvalidInput(format!(
+                "the stored {} contains a newline or NUL, which git cannot be sent", key
+            )));
+        }
+        out.push_str(&format!("{}={}\n", key, value));
+    }
+    Ok(out)
+}
+
+/// Split a URL into scheme, `host[:port]` and path; the scheme is
+/// optional so bare `example.com/team` works too.
+fn split_url(url: &str) -> (Option<&str>, &str, &str) {
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, url),
+    };
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+    let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    (scheme, host, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{EntryMetadata, Password, PasswordEntry};
+
+    fn summary(name: &str, url: &str, username: &str) -> EntrySummary {
+        let entry = PasswordEntry::new(EntryName::new(name.to_string()).unwrap(), Password::new("pw".to_string()).unwrap())
+            .with_metadata(EntryMetadata {
+                url: Some(url.to_string()),
+                username: Some(username.to_string()),
+                ..Default::default()
+            });
+        EntrySummary::from(&entry)
+    }
+
+    #[test]
+    fn picks_the_most_specific_entry_for_the_host_path_and_user() {
+        let entries = [
+            summary("gh", "https://github.com", "alice"),
+            summary("gh-work", "https://github.com/acme", "alice-acme"),
+            summary("local", "http://localhost:8080/repo.git", "dev"),
+            summary("other", "gitlab.com", "alice"),
+        ];
+        let pick = |input: &str| CredentialRequest::parse(input).unwrap().best_match(&entries).map(|e| e.name.as_str().to_string());
+
+        // Entries with a path only match when git sends one (credential.useHttpPath).
+        assert_eq!(pick("protocol=https\nhost=github.com\n\n").as_deref(), Some("gh"));
+        assert_eq!(pick("protocol=https\nhost=github.com\nusername=alice\n").as_deref(), Some("gh"));
+        assert_eq!(pick("protocol=https\nhost=github.com\npath=acme/tools.git\n").as_deref(), Some("gh-work"));
+        assert_eq!(pick("protocol=https\nhost=github.com\npath=acmeco/x.git\n").as_deref(), Some("gh"));
+        assert_eq!(pick("url=http://localhost:8080/repo.git\n").as_deref(), Some("local"));
+        assert_eq!(pick("protocol=https\nhost=localhost:8080\n").as_deref(), None);
+        assert_eq!(pick("protocol=https\nhost=example.com\n").as_deref(), None);
+
+        let request = CredentialRequest::parse("protocol=http\nhost=localhost:8080\nusername=dev\npassword=x\n").unwrap();
+        assert_eq!(request.entry_name("git").unwrap().as_str(), "git/localhost:8080/dev");
+        assert_eq!(request.url(), "http://localhost:8080");
+        assert_eq!(response(Some("dev"), "x").unwrap(), "username=dev\npassword=x\n");
+        assert_eq!(response(None, "x").unwrap(), "password=x\n");
+    }
+
+    #[test]
+    fn values_that_would_break_the_reply_are_refused() {
+        for (username, password) in [
+            (Some("dev\nhost=evil.example"), "x"),
+            (Some("dev\0"), "x"),
+            (Some("dev"), "x\npassword=y"),
+            (None, "x\0y"),
+        ] {
+            let error = response(username, password).unwrap_err();
+            assert_eq!(error.code(), "invalid_input", "{:?} {:?}", username, password);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Argon2id cost parameters, persisted in the vault so unlock uses the
+/// same cost the vault was created with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    // Matches `Argon2::default()`, which every vault before this field used.
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Highest costs accepted. The parameters come from the vault file, so
+    /// without a ceiling an edited header could make unlock try to
+    /// allocate terabytes or run for days.
+    pub const MAX_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+    pub const MAX_ITERATIONS: u32 = 100;
+    pub const MAX_PARALLELISM: u32 = 64;
+
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self> {
+        let params = Self { memory_kib, iterations, parallelism };
+        params.check_limits()?;
+        params.to_argon2_params()?;
+        Ok(params)
+    }
+
+    /// Refuse costs above the `MAX_*` ceilings.
+    pub fn check_limits(&self) -> Result<()> {
+        let over = |name: &str, value: u32, max: u32| (value > max).then(|| format!("{} {} is above the limit of {}", name, value, max));
+        let problem = over("memory", self.memory_kib, Self::MAX_MEMORY_KIB)
+            .or_else(|| over("iterations", self.iterations, Self::MAX_ITERATIONS))
+            .or_else(|| over("parallelism", self.parallelism, Self::MAX_PARALLELISM));
+        match problem {
+            Some(problem) => Err(Error::InvalidInput(format!("Invalid KDF parameters: {}", problem))),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn argon2(&self) -> Result<Argon2<'static>> {
+        self.check_limits()?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, self.to_argon2_params()?))
+    }
+
+    fn to_argon2_params(self) -> Result<Params> {
+        Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .map_err(|e| Error::Encryption(format!("Invalid KDF parameters: {}", e)))
+    }
+}
@@ -2,35 +2,46 @@ use argon2::{
     password_hash::{
         PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
     },
-    Argon2
 };
-use rand::rngs::OsRng;
+use rand::{rngs::OsRng, RngCore};
 use chacha20poly1305::{
-    aead::{Aead, AeadCore, KeyInit},
+    aead::{Aead, Generate, KeyInit, Payload},
     ChaCha20Poly1305, Nonce,
 };
-// use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-// use zeroize::{Zeroize, ZeroizeOnDrop};
 
+use crate::domain::HistoryRetention;
 use crate::error::{Error, Result};
 
+use super::kdf::KdfParams;
 use super::master_key::MasterKey;
-use super::vault::EncryptedVault;
+use super::vault::{EncryptedVault, VaultHeader, WrappedKey, CURRENT_VERSION, VERSION_PASSWORD_KEY};
 use super::encoding;
 
+const KEY_LEN: usize = 32;
 
+/// Holds the key that encrypts the vault payload while the vault is
+/// unlocked. For v2 vaults that is the random data-encryption key (DEK);
+/// the password-derived key-encryption key (KEK) only lives long enough
+/// to wrap or unwrap it.
 pub struct CryptoManager {
     master_key: Option<MasterKey>,
 }
 
+impl Default for CryptoManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CryptoManager {
 
     pub fn new() -> Self{
         Self {master_key: None}
     }
 
-    pub fn derive_master_key(&mut self, password: &str, salt: Option<&str>) -> Result<String> {
+    /// v1 key derivation: the payload key is a hash of the Argon2 output.
+    pub fn derive_master_key(&mut self, password: &str, salt: Option<&str>, kdf: &KdfParams) -> Result<String> {
         // use provided salt or generate a new one
         let salt_string = match salt {
             Some(s) => SaltString::from_b64(s)
@@ -39,102 +50,191 @@ impl CryptoManager {
         };
 
         // Define Argon2 
-        let argon2 = Argon2::default();
+        let argon2 = kdf.argon2()?;
 
         // Hash Password
         let password_hash = argon2.hash_password(password.as_bytes(), &salt_string)
             .map_err(|e| Error::Encryption(format!("Failed to hash password: {}", e)))?;
 
-        let hash_bytes = password_hash.hash.unwrap();
-        let mut key = vec![0u8; 32];
+        let hash_bytes = password_hash.hash
+            .ok_or_else(|| Error::Encryption("Argon2 produced no hash".to_string()))?;
+        let mut key = vec![0u8; KEY_LEN];
 
         let mut hasher = Sha256::new();
         hasher.update(hash_bytes.as_bytes());
         let result = hasher.finalize();
-        key.copy_from_slice(&result[..32]);
+        key.copy_from_slice(&result[..KEY_LEN]);
 
-        self.master_key = Some(MasterKey {key: key.clone()});
+        self.master_key = Some(MasterKey {key});
 
         Ok(salt_string.to_string())
     }
 
+    /// v1 password check against the stored PHC hash.
     pub fn verify_password(&mut self, password: &str, vault: &EncryptedVault) -> Result<()> {
-        let parsed_hash = PasswordHash::new(&vault.argon2_params)
+        let stored = vault.header.argon2_params.as_deref()
+            .ok_or_else(|| Error::Encryption("Vault has no password hash".to_string()))?;
+        let parsed_hash = PasswordHash::new(stored)
             .map_err(|e| Error::Encryption(format!("Invalid stored Hash: {}", e)))?;
+        // Verification uses the cost written in the hash, not `kdf`.
+        let hashed = argon2::Params::try_from(&parsed_hash)
+            .map_err(|e| Error::Encryption(format!("Invalid stored Hash: {}", e)))?;
+        KdfParams { memory_kib: hashed.m_cost(), iterations: hashed.t_cost(), parallelism: hashed.p_cost() }
+            .check_limits()?;
 
-        Argon2::default()
+        vault.header.kdf.argon2()?
             .verify_password(password.as_bytes(), &parsed_hash)
             .map_err(|_| Error::Encryption("Invalid master password".to_string()))?;
 
-        self.derive_master_key(password, Some(&vault.salt))?;
+        self.derive_master_key(password, Some(&vault.header.salt), &vault.header.kdf)?;
 
         Ok(())
     }
 
+    /// Derive the key-encryption key straight from Argon2's raw output.
+    fn derive_kek(password: &str, salt: &str, kdf: &KdfParams) -> Result<MasterKey> {
+        let mut key = vec![0u8; KEY_LEN];
+        kdf.argon2()?
+            .hash_password_into(password.as_bytes(), salt.as_bytes(), &mut key)
+            .map_err(|e| Error::Encryption(format!("Failed to hash password: {}", e)))?;
+        Ok(MasterKey {key})
+    }
 
-    pub fn encrypt(&self, plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
-        let master_key = self.master_key.as_ref()
-            .ok_or_else(|| Error::Encryption("No master key set".to_string()))?;
-
-        let cipher = ChaCha20Poly1305::new_from_slice(&master_key.key)
-            .map_err(|e| Error::Encryption(format!("Failed to create cipher: {}", e)))?;
+    /// Wrap the data key, binding it to `aad` (the header's
+    /// `key_associated_data`).
+    fn wrap_key(kek: &MasterKey, dek: &MasterKey, aad: &[u8]) -> Result<WrappedKey> {
+        let (nonce, ciphertext) = seal(kek, &dek.key, aad)?;
+        Ok(WrappedKey {
+            nonce: encoding::encode(&nonce),
+            ciphertext: encoding::encode(&ciphertext),
+        })
+    }
 
-        let nonce = ChaCha20Poly1305::generate_nonce()
-            .map_err(|e| Error::Encryption(format!("Failed to generate nonce: {}", e)))?;
+    fn unwrap_key(kek: &MasterKey, wrapped: &WrappedKey, aad: &[u8]) -> Result<MasterKey> {
+        let nonce = encoding::decode(&wrapped.nonce)
+            .map_err(|e| Error::Encryption(format!("Invalid key nonce: {}", e)))?;
+        let ciphertext = encoding::decode(&wrapped.ciphertext)
+            .map_err(|e| Error::Encryption(format!("Invalid wrapped key: {}", e)))?;
 
-        let ciphertext = cipher.encrypt(&nonce, plaintext)
-            .map_err(|e| Error::Encryption(format!("Encryption failed: {}", e)))?;
-        
-        Ok((nonce.to_vec(), ciphertext))
+        // The wrapped key's tag doubles as the password check.
+        let key = open(kek, &nonce, &ciphertext, aad)
+            .map_err(|_| Error::Encryption("Invalid master password".to_string()))?;
+        Ok(MasterKey {key})
     }
 
-    pub fn decrypt(&self, nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
         let master_key = self.master_key.as_ref()
             .ok_or_else(|| Error::Encryption("No master key set".to_string()))?;
 
-        let cipher = ChaCha20Poly1305::new_from_slice(&master_key.key)
-            .map_err(|e| Error::Encryption(format!("Failed to create cipher: {}", e)))?;
-
-        let nonce = Nonce::try_from(nonce)
-            .map_err(|_| Error::Encryption("Invalid nonce length".to_string()))?;
-
-        let plaintext = cipher.decrypt(&nonce, ciphertext)
-            .map_err(|_| Error::Encryption("Decryption failed - wrong password?".to_string()))?;
-
-        Ok(plaintext)
+        seal(master_key, plaintext, aad)
     }
 
-    pub fn create_vault(&mut self, password: &str, data: &[u8]) -> Result<EncryptedVault> {
-        let salt = self.derive_master_key(password, None)?;
-
-        let argon2 = Argon2::default();
-        let salt_obj = SaltString::from_b64(&salt)
-            .map_err(|e| Error::Encryption(format!("Invalid salt: {}", e)))?;
+    pub fn decrypt(&self, nonce: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let master_key = self.master_key.as_ref()
+            .ok_or_else(|| Error::Encryption("No master key set".to_string()))?;
 
-        let password_hash = argon2.hash_password(password.as_bytes(), &salt_obj)
-            .map_err(|e| Error::Encryption(format!("Failed to hash password: {}", e)))?;
+        open(master_key, nonce, ciphertext, aad)
+    }
 
-        let (nonce, ciphertext) = self.encrypt(data)?;
+    /// Encrypt `data` as the payload belonging to `header`.
+    pub fn seal_vault(&self, mut header: VaultHeader, data: &[u8]) -> Result<EncryptedVault> {
+        header.digest = header.has_digest().then(|| header.compute_digest());
+        let aad = payload_aad(&header);
+        let (nonce, ciphertext) = self.encrypt(data, &aad)?;
 
         Ok(EncryptedVault {
-            salt,
-            argon2_params: password_hash.to_string(),
+            header,
             nonce: encoding::encode(&nonce),
             ciphertext: encoding::encode(&ciphertext),
-            version: 1
         })
     }
 
+    /// Create a current-version vault: fresh salt, fresh random DEK.
+    pub fn create_vault(&mut self, password: &str, data: &[u8], kdf: KdfParams) -> Result<EncryptedVault> {
+        let salt = SaltString::generate(OsRng).to_string();
+        let kek = Self::derive_kek(password, &salt, &kdf)?;
+
+        let mut dek = MasterKey {key: vec![0u8; KEY_LEN]};
+        OsRng.fill_bytes(&mut dek.key);
+
+        let mut header = VaultHeader {
+            version: CURRENT_VERSION,
+            salt,
+            argon2_params: None,
+            kdf,
+            wrapped_key: None,
+            generation: 0,
+            history: HistoryRetention::default(),
+            digest: None,
+        };
+        header.wrapped_key = Some(Self::wrap_key(&kek, &dek, &header.key_associated_data())?);
+
+        self.master_key = Some(dek);
+        self.seal_vault(header, data)
+    }
+
+    /// Unlock `vault` and return its plaintext payload.
+    ///
+    /// A header that does not match its digest is `Error::HeaderTampered`
+    /// before the password is tried, so a modified salt or KDF parameter
+    /// is not reported as a wrong password. Vaults from before the digest
+    /// can only tell once the data key is recovered.
     pub fn open_vault(&mut self, password: &str, vault: &EncryptedVault) -> Result<Vec<u8>> {
-        self.verify_password(password, vault)?;
+        let header = &vault.header;
+        header.check_digest()?;
+        if header.version > CURRENT_VERSION {
+            return Err(Error::Encryption(format!("Unsupported vault version {}", header.version)));
+        }
+        // The digest is unkeyed, so an edited cost may carry a valid one.
+        header.kdf.check_limits().map_err(|_| Error::HeaderTampered)?;
+
+        if header.version == VERSION_PASSWORD_KEY {
+            self.verify_password(password, vault)?;
+        } else {
+            let wrapped = header.wrapped_key.as_ref()
+                .ok_or_else(|| Error::Encryption("Vault has no wrapped key".to_string()))?;
+            let kek = Self::derive_kek(password, &header.salt, &header.kdf)?;
+            self.master_key = Some(Self::unwrap_key(&kek, wrapped, &header.key_associated_data())?);
+        }
+
+        self.reopen_vault(vault)
+    }
 
+    /// Decrypt the payload of `vault` with the key already held, e.g. to
+    /// pick up a newer copy of the vault written by another process.
+    pub fn reopen_vault(&self, vault: &EncryptedVault) -> Result<Vec<u8>> {
+        vault.header.check_digest()?;
         let nonce = encoding::decode(&vault.nonce)
             .map_err(|e| Error::Encryption(format!("Invalid nonce: {}", e )))?;
 
         let ciphertext = encoding::decode(&vault.ciphertext)
             .map_err(|e| Error::Encryption(format!("Invalid ciphertext: {}", e)))?;
 
-        self.decrypt(&nonce, &ciphertext)
+        self.decrypt(&nonce, &ciphertext, &payload_aad(&vault.header))
+            .map_err(|_| Error::HeaderTampered)
+    }
+
+    /// Wrap the current DEK under a key derived from `new_password` with
+    /// `kdf`. The data key stays the same; `data` is only re-sealed because
+    /// the payload is bound to the new header.
+    pub fn rewrap_vault(&self, vault: &EncryptedVault, new_password: &str, kdf: KdfParams, data: &[u8]) -> Result<EncryptedVault> {
+        if vault.needs_upgrade() {
+            return Err(Error::Encryption("Vault must be upgraded before rewrapping".to_string()));
+        }
+        let dek = self.master_key.as_ref()
+            .ok_or_else(|| Error::Encryption("No master key set".to_string()))?;
+
+        let salt = SaltString::generate(OsRng).to_string();
+        let kek = Self::derive_kek(new_password, &salt, &kdf)?;
+
+        let mut header = VaultHeader {
+            salt,
+            kdf,
+            wrapped_key: None,
+            ..vault.header.clone()
+        };
+        header.wrapped_key = Some(Self::wrap_key(&kek, dek, &header.key_associated_data())?);
+        self.seal_vault(header, data)
     }
 
     pub fn is_unlocked(&self) -> bool {
@@ -146,5 +246,224 @@ impl CryptoManager {
     }
 }
 
+/// Older vaults predate header authentication and used no associated data.
+fn payload_aad(header: &VaultHeader) -> Vec<u8> {
+    if header.is_authenticated() {
+        header.associated_data()
+    } else {
+        Vec::new()
+    }
+}
+
+fn seal(key: &MasterKey, plaintext: &[u8], aad: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let cipher = ChaCha20Poly1305::new_from_slice(&key.key)
+        .map_err(|e| Error::Encryption(format!("Failed to create cipher: {}", e)))?;
 
+    let nonce = Nonce::try_generate()
+        .map_err(|e| Error::Encryption(format!("Failed to generate nonce: {}", e)))?;
 
+    let ciphertext = cipher.encrypt(&nonce, Payload { msg: plaintext, aad })
+        .map_err(|e| Error::Encryption(format!("Encryption failed: {}", e)))?;
+
+    Ok((nonce.to_vec(), ciphertext))
+}
+
+fn open(key: &MasterKey, nonce: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    let cipher = ChaCha20Poly1305::new_from_slice(&key.key)
+        .map_err(|e| Error::Encryption(format!("Failed to create cipher: {}", e)))?;
+
+    let nonce = Nonce::try_from(nonce)
+        .map_err(|_| Error::Encryption("Invalid nonce length".to_string()))?;
+
+    cipher.decrypt(&nonce, Payload { msg: ciphertext, aad })
+        .map_err(|_| Error::Encryption("Decryption failed - wrong password?".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::vault::{VERSION_AUTHENTICATED_HEADER, VERSION_WRAPPED_KEY};
+
+    fn cheap_kdf() -> KdfParams {
+        KdfParams::new(8, 1, 1).unwrap()
+    }
+
+    #[test]
+    fn create_and_open_roundtrip() {
+        let mut crypto = CryptoManager::new();
+        let vault = crypto.create_vault("correct horse", b"payload", cheap_kdf()).unwrap();
+        assert_eq!(vault.header.version, CURRENT_VERSION);
+        assert!(vault.header.argon2_params.is_none());
+
+        let mut other = CryptoManager::new();
+        assert_eq!(other.open_vault("correct horse", &vault).unwrap(), b"payload");
+        assert!(other.open_vault("wrong horse", &vault).is_err());
+    }
+
+    #[test]
+    fn rewrap_changes_password_but_keeps_data_key() {
+        let mut crypto = CryptoManager::new();
+        let vault = crypto.create_vault("old password", b"payload", cheap_kdf()).unwrap();
+        let rewrapped = crypto.rewrap_vault(&vault, "new password", cheap_kdf(), b"payload").unwrap();
+
+        assert_ne!(rewrapped.header.salt, vault.header.salt);
+        assert_ne!(rewrapped.header.wrapped_key, vault.header.wrapped_key);
+
+        let mut other = CryptoManager::new();
+        assert!(other.open_vault("old password", &rewrapped).is_err());
+        assert_eq!(other.open_vault("new password", &rewrapped).unwrap(), b"payload");
+
+        // The same DEK still opens a payload sealed before the rewrap.
+        let aad = payload_aad(&vault.header);
+        let nonce = encoding::decode(&vault.nonce).unwrap();
+        let ciphertext = encoding::decode(&vault.ciphertext).unwrap();
+        assert_eq!(other.decrypt(&nonce, &ciphertext, &aad).unwrap(), b"payload");
+    }
+
+    fn sealed() -> EncryptedVault {
+        CryptoManager::new().create_vault("correct horse", b"payload", cheap_kdf()).unwrap()
+    }
+
+    fn open_with(vault: &EncryptedVault) -> Result<Vec<u8>> {
+        CryptoManager::new().open_vault("correct horse", vault)
+    }
+
+    #[test]
+    fn header_survives_reformatting() {
+        let vault = sealed();
+        let compact = serde_json::to_string(&vault).unwrap();
+        let reparsed: EncryptedVault = serde_json::from_str(&compact).unwrap();
+        assert_eq!(open_with(&reparsed).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn downgraded_version_is_reported_as_tampering() {
+        let mut vault = sealed();
+        vault.header.version = VERSION_WRAPPED_KEY;
+        assert!(matches!(open_with(&vault), Err(Error::HeaderTampered)));
+    }
+
+    #[test]
+    fn future_version_is_rejected() {
+        let mut vault = sealed();
+        vault.header.version = CURRENT_VERSION + 1;
+        assert!(open_with(&vault).is_err());
+    }
+
+    #[test]
+    fn modified_kdf_params_are_reported_as_tampering() {
+        let mut vault = sealed();
+        vault.header.kdf.iterations += 1;
+        assert!(matches!(open_with(&vault), Err(Error::HeaderTampered)));
+    }
+
+    #[test]
+    fn oversized_kdf_params_are_refused_before_deriving() {
+        let mut vault = sealed();
+        vault.header.kdf.memory_kib = u32::MAX;
+        vault.header.digest = Some(vault.header.compute_digest());
+        assert!(matches!(open_with(&vault), Err(Error::HeaderTampered)));
+
+        assert!(KdfParams::new(KdfParams::MAX_MEMORY_KIB + 1, 1, 1).is_err());
+        assert!(KdfParams::new(8, KdfParams::MAX_ITERATIONS + 1, 1).is_err());
+        assert!(KdfParams::new(8, 1, KdfParams::MAX_PARALLELISM + 1).is_err());
+        assert!(KdfParams::new(KdfParams::MAX_MEMORY_KIB, KdfParams::MAX_ITERATIONS, KdfParams::MAX_PARALLELISM).is_ok());
+    }
+
+    #[test]
+    fn flipped_salt_byte_is_reported_as_tampering() {
+        let mut vault = sealed();
+        let mut salt = vault.header.salt.into_bytes();
+        salt[0] = if salt[0] == b'A' { b'B' } else { b'A' };
+        vault.header.salt = String::from_utf8(salt).unwrap();
+        assert!(matches!(open_with(&vault), Err(Error::HeaderTampered)));
+    }
+
+    #[test]
+    fn wrong_password_is_not_tampering() {
+        let vault = sealed();
+        assert!(matches!(CryptoManager::new().open_vault("wrong horse", &vault), Err(Error::Encryption(_))));
+    }
+
+    #[test]
+    fn vaults_without_a_digest_still_open() {
+        let kdf = cheap_kdf();
+        let salt = SaltString::generate(OsRng).to_string();
+        let kek = CryptoManager::derive_kek("correct horse", &salt, &kdf).unwrap();
+        let dek = MasterKey {key: vec![7u8; KEY_LEN]};
+        let header = VaultHeader {
+            version: VERSION_AUTHENTICATED_HEADER,
+            salt,
+            argon2_params: None,
+            kdf,
+            wrapped_key: Some(CryptoManager::wrap_key(&kek, &dek, &[]).unwrap()),
+            generation: 3,
+            history: HistoryRetention::default(),
+            digest: None,
+        };
+        let crypto = CryptoManager {master_key: Some(dek)};
+        let vault = crypto.seal_vault(header, b"payload").unwrap();
+        assert!(vault.header.digest.is_none());
+        assert!(vault.needs_upgrade());
+        assert_eq!(open_with(&vault).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn header_bytes_bind_every_field() {
+        let header = sealed().header;
+        let base = header.associated_data();
+
+        let mut versions = header.clone();
+        versions.version ^= 1;
+        let mut salts = header.clone();
+        salts.salt.push('x');
+        let mut kdfs = header.clone();
+        kdfs.kdf.memory_kib ^= 1;
+        let mut keys = header.clone();
+        keys.wrapped_key = None;
+        let mut generations = header.clone();
+        generations.generation += 1;
+        let mut histories = header.clone();
+        histories.history.max_age_days = Some(30);
+
+        for changed in [versions, salts, kdfs, keys, generations, histories] {
+            assert_ne!(changed.associated_data(), base);
+        }
+    }
+
+    #[test]
+    fn flipping_any_header_byte_on_disk_is_detected() {
+        let mut crypto = CryptoManager::new();
+        let fresh = crypto.create_vault("correct horse", b"payload", cheap_kdf()).unwrap();
+        let mut header = fresh.header.clone();
+        header.generation = 5;
+        header.history = HistoryRetention { max_versions: 3, max_age_days: Some(30) };
+        let busy = crypto.seal_vault(header, b"payload").unwrap();
+
+        for vault in [fresh, busy] {
+            let json = serde_json::to_vec(&vault).unwrap();
+            // The payload's nonce follows the header; the wrapped key has one too.
+            let header_end = json.windows(7).rposition(|w| w == b"\"nonce\"").unwrap();
+            let mut fields = std::collections::BTreeSet::new();
+
+            for i in 0..header_end {
+                let mut tampered = json.clone();
+                tampered[i] ^= 0x01;
+                let Ok(parsed) = serde_json::from_slice::<EncryptedVault>(&tampered) else {
+                    continue;
+                };
+                if parsed.header == vault.header {
+                    continue;
+                }
+                assert!(matches!(open_with(&parsed), Err(Error::HeaderTampered)), "byte {} flipped undetected", i);
+                let (before, after) = (serde_json::to_value(&vault.header).unwrap(), serde_json::to_value(&parsed.header).unwrap());
+                fields.extend(before.as_object().unwrap().keys().filter(|name| after.get(name.as_str()) != before.get(name.as_str())).cloned());
+            }
+            let expected: Vec<&str> = match vault.header.history.is_default() {
+                true => vec!["digest", "generation", "kdf", "salt", "version", "wrapped_key"],
+                false => vec!["digest", "generation", "history", "kdf", "salt", "version", "wrapped_key"],
+            };
+            assert_eq!(fields.iter().map(String::as_str).collect::<Vec<_>>(), expected);
+        }
+    }
+}
@@ -1,7 +1,12 @@
+mod kdf;
 mod master_key;
 mod manager;
 mod vault;
 pub mod encoding;
 
+pub use kdf::KdfParams;
 pub use manager::CryptoManager;
-pub use vault::EncryptedVault;
\ No newline at end of file
+pub use vault::{
+    EncryptedVault, VaultHeader, WrappedKey, CURRENT_VERSION, VERSION_AUTHENTICATED_HEADER,
+    VERSION_HEADER_DIGEST, VERSION_PASSWORD_KEY, VERSION_WRAPPED_KEY,
+};
\ No newline at end of file
@@ -1,10 +1,154 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-#[derive(Debug, Serialize, Deserialize)]
+use super::encoding;
+use super::kdf::KdfParams;
+use crate::domain::HistoryRetention;
+use crate::error::{Error, Result};
+
+/// Vault layout where the payload key is the password-derived key.
+pub const VERSION_PASSWORD_KEY: u32 = 1;
+/// Vault layout where a random data key encrypts the payload and is
+/// itself wrapped by the password-derived key.
+pub const VERSION_WRAPPED_KEY: u32 = 2;
+/// Wrapped-key layout whose header is bound to the payload as AEAD
+/// associated data.
+pub const VERSION_AUTHENTICATED_HEADER: u32 = 3;
+/// Authenticated-header layout that also carries a digest of the header,
+/// checked before the password is tried, and binds the salt and KDF
+/// parameters to the wrapped key.
+pub const VERSION_HEADER_DIGEST: u32 = 4;
+pub const CURRENT_VERSION: u32 = VERSION_HEADER_DIGEST;
+
+const HEADER_DOMAIN: &[u8] = b"powda-vault-header";
+const KEY_DOMAIN: &[u8] = b"powda-vault-key";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedVault {
-    pub salt: String,
-    pub argon2_params: String,
+    #[serde(flatten)]
+    pub header: VaultHeader,
     pub nonce: String,
     pub ciphertext: String,
+}
+
+/// Everything in the vault file except the encrypted payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultHeader {
     pub version: u32,
-}
\ No newline at end of file
+    pub salt: String,
+    /// PHC hash used to check the password; only present in v1 vaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub argon2_params: Option<String>,
+    #[serde(default)]
+    pub kdf: KdfParams,
+    /// Data-encryption key wrapped by the key-encryption key; v2 and later.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wrapped_key: Option<WrappedKey>,
+    /// Bumped on every write so other processes can tell the vault moved on.
+    #[serde(default)]
+    pub generation: u64,
+    /// Password history kept per entry.
+    #[serde(default, skip_serializing_if = "HistoryRetention::is_default")]
+    pub history: HistoryRetention,
+    /// SHA-256 of `associated_data`, set when the vault is sealed; v4 and later.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WrappedKey {
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl EncryptedVault {
+    pub fn needs_upgrade(&self) -> bool {
+        self.header.version < CURRENT_VERSION
+    }
+}
+
+impl VaultHeader {
+    pub fn is_authenticated(&self) -> bool {
+        self.version >= VERSION_AUTHENTICATED_HEADER
+    }
+
+    pub fn has_digest(&self) -> bool {
+        self.version >= VERSION_HEADER_DIGEST
+    }
+
+    pub fn compute_digest(&self) -> String {
+        encoding::encode(&Sha256::digest(self.associated_data()))
+    }
+
+    /// A wrong salt or KDF parameter only yields a wrong key, which looks
+    /// like a wrong password; the digest tells the two apart. It is not
+    /// keyed, so it catches corruption and edits, not someone who also
+    /// recomputes it - they still cannot get past the password.
+    pub fn check_digest(&self) -> Result<()> {
+        match &self.digest {
+            Some(digest) if *digest == self.compute_digest() => Ok(()),
+            None if !self.has_digest() => Ok(()),
+            _ => Err(Error::HeaderTampered),
+        }
+    }
+
+    /// Associated data for wrapping the data key: the header fields the
+    /// key-encryption key is derived from. Empty before v4.
+    pub fn key_associated_data(&self) -> Vec<u8> {
+        if !self.has_digest() {
+            return Vec::new();
+        }
+        let mut out = Vec::with_capacity(64);
+        put_bytes(&mut out, KEY_DOMAIN);
+        out.extend_from_slice(&self.version.to_be_bytes());
+        put_bytes(&mut out, self.salt.as_bytes());
+        out.extend_from_slice(&self.kdf.memory_kib.to_be_bytes());
+        out.extend_from_slice(&self.kdf.iterations.to_be_bytes());
+        out.extend_from_slice(&self.kdf.parallelism.to_be_bytes());
+        out
+    }
+
+    /// Canonical encoding of the header used as associated data for the
+    /// payload. Every field is length-prefixed so the bytes do not depend
+    /// on how the JSON happened to be formatted.
+    pub fn associated_data(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(128);
+        put_bytes(&mut out, HEADER_DOMAIN);
+        out.extend_from_slice(&self.version.to_be_bytes());
+        put_bytes(&mut out, self.salt.as_bytes());
+        out.extend_from_slice(&self.kdf.memory_kib.to_be_bytes());
+        out.extend_from_slice(&self.kdf.iterations.to_be_bytes());
+        out.extend_from_slice(&self.kdf.parallelism.to_be_bytes());
+        match &self.wrapped_key {
+            Some(wrapped) => {
+                out.push(1);
+                put_bytes(&mut out, wrapped.nonce.as_bytes());
+                put_bytes(&mut out, wrapped.ciphertext.as_bytes());
+            }
+            None => out.push(0),
+        }
+        // Fields added after v3 are only encoded once they leave their
+        // default, so vaults written before them still authenticate.
+        if self.generation != 0 {
+            put_bytes(&mut out, b"generation");
+            out.extend_from_slice(&self.generation.to_be_bytes());
+        }
+        if !self.history.is_default() {
+            put_bytes(&mut out, b"history");
+            out.extend_from_slice(&(self.history.max_versions as u64).to_be_bytes());
+            match self.history.max_age_days {
+                Some(days) => {
+                    out.push(1);
+                    out.extend_from_slice(&days.to_be_bytes());
+                }
+                None => out.push(0),
+            }
+        }
+        out
+    }
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
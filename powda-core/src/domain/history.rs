@@ -0,0 +1,66 @@
+use super::value_objects::Password;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+/// A password an entry used before, and when it was replaced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordVersion {
+    pub password: Password,
+    pub replaced_at: SystemTime,
+}
+
+/// Vault-wide limits on how much password history each entry keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryRetention {
+    pub max_versions: usize,
+    /// Drop versions replaced longer ago than this; `None` keeps them.
+    pub max_age_days: Option<u32>,
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self { max_versions: 10, max_age_days: None }
+    }
+}
+
+/// A partial update to a `HistoryRetention`; unset fields keep their value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionChange {
+    pub max_versions: Option<usize>,
+    /// `Some(0)` lifts the age limit.
+    pub max_age_days: Option<u32>,
+}
+
+impl RetentionChange {
+    pub fn is_empty(&self) -> bool {
+        self.max_versions.is_none() && self.max_age_days.is_none()
+    }
+
+    pub fn apply_to(&self, retention: HistoryRetention) -> HistoryRetention {
+        HistoryRetention {
+            max_versions: self.max_versions.unwrap_or(retention.max_versions),
+            max_age_days: match self.max_age_days {
+                Some(0) => None,
+                Some(days) => Some(days),
+                None => retention.max_age_days,
+            },
+        }
+    }
+}
+
+impl HistoryRetention {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Trim `history` (newest first) to this policy as of `now`.
+    pub fn apply(&self, history: &mut Vec<PasswordVersion>, now: SystemTime) {
+        history.truncate(self.max_versions);
+        if let Some(days) = self.max_age_days {
+            let max_age = Duration::from_secs(u64::from(days) * 86_400);
+            history.retain(|version| {
+                now.duration_since(version.replaced_at).map_or(true, |age| age <= max_age)
+            });
+        }
+    }
+}
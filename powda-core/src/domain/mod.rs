@@ -1,6 +1,16 @@
 
+mod history;
+mod otp;
 mod password_entry;
+mod query;
+mod ssh;
+mod tree;
 mod value_objects;
 
-pub use password_entry::PasswordEntry;
-pub use value_objects::{EntryName, Password};
+pub use history::{HistoryRetention, PasswordVersion, RetentionChange};
+pub use otp::{OtpAlgorithm, OtpCode, OtpKind, OtpSecret};
+pub use password_entry::{EntryMetadata, PasswordEntry};
+pub use query::{url_host, EntryQuery, EntrySummary};
+pub use ssh::SshKeyInfo;
+pub use tree::{NameTree, TreeRow, TreeRowKind};
+pub use value_objects::{EntryName, Password, FOLDER_SEPARATOR};
@@ -1,5 +1,9 @@
 
+use super::history::PasswordVersion;
+use super::otp::OtpSecret;
+use super::ssh::SshKeyInfo;
 use super::value_objects::{EntryName, Password};
+use crate::error::{Error, Result};
 use serde::{Deserialize, Serialize};
 use std::time::SystemTime;
 
@@ -9,7 +13,15 @@ pub struct PasswordEntry {
     pub password: Password,
     pub created_at: SystemTime,
     pub modified_at: SystemTime,
-    pub metadata: EntryMetadata
+    pub metadata: EntryMetadata,
+    #[serde(default)]
+    pub otp: Option<OtpSecret>,
+    /// Set when the password is an SSH private key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh: Option<SshKeyInfo>,
+    /// Earlier passwords, newest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<PasswordVersion>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -29,11 +41,72 @@ impl PasswordEntry {
             created_at: now,
             modified_at: now,
             metadata: EntryMetadata::default(),
+            otp: None,
+            ssh: None,
+            history: Vec::new(),
         }
     }
 
+    pub fn with_metadata(mut self, metadata: EntryMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Replace the password, keeping the old one in the history.
     pub fn update_password(&mut self, password: Password) {
-        self.password = password;
+        let now = SystemTime::now();
+        if password.as_str() != self.password.as_str() {
+            let previous = std::mem::replace(&mut self.password, password);
+            self.history.insert(0, PasswordVersion { password: previous, replaced_at: now });
+        }
+        self.modified_at = now;
+    }
+
+    /// Make history version `version` (1 is the most recent) current again.
+    /// The password it replaces goes into the history like any other change.
+    pub fn restore_version(&mut self, version: usize) -> Result<()> {
+        if version == 0 || version > self.history.len() {
+            return Err(Error::NotFound(format!("{} version {}", self.name.as_str(), version)));
+        }
+        let restored = self.history.remove(version - 1);
+        self.update_password(restored.password.clone());
+        Ok(())
+    }
+
+    pub fn update_otp(&mut self, otp: Option<OtpSecret>) {
+        self.otp = otp;
         self.modified_at = SystemTime::now();
     }
+
+    pub fn update_metadata(&mut self, metadata: EntryMetadata) {
+        self.metadata = metadata;
+        self.modified_at = SystemTime::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn password(value: &str) -> Password {
+        Password::new(value.to_string()).unwrap()
+    }
+
+    #[test]
+    fn restoring_a_version_keeps_the_replaced_password() {
+        let mut entry = PasswordEntry::new(EntryName::new("db".to_string()).unwrap(), password("one"));
+        entry.update_password(password("two"));
+        entry.update_password(password("three"));
+        entry.update_password(password("three"));
+
+        let history: Vec<&str> = entry.history.iter().map(|v| v.password.as_str()).collect();
+        assert_eq!(history, ["two", "one"]);
+
+        entry.restore_version(2).unwrap();
+        assert_eq!(entry.password.as_str(), "one");
+        let history: Vec<&str> = entry.history.iter().map(|v| v.password.as_str()).collect();
+        assert_eq!(history, ["three", "two"]);
+
+        assert!(matches!(entry.restore_version(3), Err(Error::NotFound(_))));
+    }
 }
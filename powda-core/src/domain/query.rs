@@ -0,0 +1,160 @@
+use super::password_entry::PasswordEntry;
+use super::ssh::SshKeyInfo;
+use super::value_objects::{EntryName, FOLDER_SEPARATOR};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Filters for `StoreRepository::query`. Every filter that is set must
+/// match; an empty query matches every entry.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EntryQuery {
+    /// Glob over the full name: `*` and `?` stay within one folder, `**`
+    /// crosses folders. A pattern without `/` is matched against the last
+    /// segment in any folder, so `git*` finds `dev/github`.
+    pub name: Option<String>,
+    /// Tags the entry must all carry, compared case-insensitively.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Host of the entry's URL; subdomains match too.
+    pub host: Option<String>,
+    /// Case-insensitive substring of the username.
+    pub username: Option<String>,
+    pub created_after: Option<SystemTime>,
+    pub created_before: Option<SystemTime>,
+    pub modified_after: Option<SystemTime>,
+    pub modified_before: Option<SystemTime>,
+}
+
+impl EntryQuery {
+    pub fn matches(&self, entry: &PasswordEntry) -> bool {
+        let metadata = &entry.metadata;
+
+        self.name.as_deref().is_none_or(|pattern| name_matches(pattern, &entry.name))
+            && self.tags.iter().all(|tag| metadata.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+            && self.host.as_deref().is_none_or(|wanted| {
+                metadata.url.as_deref().and_then(url_host).is_some_and(|host| host_matches(wanted, host))
+            })
+            && self.username.as_deref().is_none_or(|wanted| {
+                metadata.username.as_deref()
+                    .is_some_and(|username| username.to_lowercase().contains(&wanted.to_lowercase()))
+            })
+            && self.created_after.is_none_or(|after| entry.created_at >= after)
+            && self.created_before.is_none_or(|before| entry.created_at < before)
+            && self.modified_after.is_none_or(|after| entry.modified_at >= after)
+            && self.modified_before.is_none_or(|before| entry.modified_at < before)
+    }
+}
+
+/// What `query` returns: everything needed to pick an entry, but no
+/// password, history, notes or OTP seed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntrySummary {
+    pub name: EntryName,
+    pub url: Option<String>,
+    pub username: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: SystemTime,
+    pub modified_at: SystemTime,
+    pub has_otp: bool,
+    /// Public half of an SSH key entry.
+    #[serde(default)]
+    pub ssh: Option<SshKeyInfo>,
+}
+
+impl From<&PasswordEntry> for EntrySummary {
+    fn from(entry: &PasswordEntry) -> Self {
+        Self {
+            name: entry.name.clone(),
+            url: entry.metadata.url.clone(),
+            username: entry.metadata.username.clone(),
+            tags: entry.metadata.tags.clone(),
+            created_at: entry.created_at,
+            modified_at: entry.modified_at,
+            has_otp: entry.otp.is_some(),
+            ssh: entry.ssh.clone(),
+        }
+    }
+}
+
+/// Host part of a URL, with or without a scheme, userinfo or port.
+pub fn url_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+fn host_matches(wanted: &str, host: &str) -> bool {
+    let (wanted, host) = (wanted.to_ascii_lowercase(), host.to_ascii_lowercase());
+    host == wanted || host.strip_suffix(&wanted).is_some_and(|sub| sub.ends_with('.'))
+}
+
+fn name_matches(pattern: &str, name: &EntryName) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.as_str().to_lowercase();
+    if pattern.contains(FOLDER_SEPARATOR) {
+        glob(pattern.as_bytes(), name.as_bytes())
+    } else {
+        let leaf = name.rsplit(FOLDER_SEPARATOR).next().unwrap_or(&name);
+        glob(pattern.as_bytes(), leaf.as_bytes())
+    }
+}
+
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob(rest, &text[i..])),
+        [b'*', rest @ ..] => {
+            let segment = text.iter().position(|&c| c == b'/').unwrap_or(text.len());
+            (0..=segment).any(|i| glob(rest, &text[i..]))
+        }
+        [b'?', rest @ ..] => match text.first() {
+            Some(&c) if c != b'/' => {
+                // Step over a whole UTF-8 character.
+                let width = 1 + text[1..].iter().take_while(|&&b| b & 0xC0 == 0x80).count();
+                glob(rest, &text[width..])
+            }
+            _ => false,
+        },
+        [p, rest @ ..] => text.first() == Some(p) && glob(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{EntryMetadata, Password};
+    use std::time::Duration;
+
+    fn entry(name: &str, url: &str, username: &str, tags: &[&str]) -> PasswordEntry {
+        PasswordEntry::new(EntryName::new(name.to_string()).unwrap(), Password::new("pw".to_string()).unwrap())
+            .with_metadata(EntryMetadata {
+                url: Some(url.to_string()),
+                username: Some(username.to_string()),
+                notes: None,
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+            })
+    }
+
+    #[test]
+    fn filters_combine() {
+        let aws = entry("work/aws/prod-root", "https://signin.aws.amazon.com/console", "Root", &["Cloud", "prod"]);
+        let query = |q: EntryQuery| q.matches(&aws);
+
+        assert!(query(EntryQuery::default()));
+        assert!(query(EntryQuery { name: Some("prod-*".to_string()), ..Default::default() }));
+        assert!(query(EntryQuery { name: Some("work/**".to_string()), ..Default::default() }));
+        assert!(!query(EntryQuery { name: Some("work/*".to_string()), ..Default::default() }));
+        assert!(query(EntryQuery { name: Some("work/?ws/*".to_string()), ..Default::default() }));
+        assert!(query(EntryQuery { tags: vec!["cloud".to_string(), "prod".to_string()], ..Default::default() }));
+        assert!(!query(EntryQuery { tags: vec!["cloud".to_string(), "dev".to_string()], ..Default::default() }));
+        assert!(query(EntryQuery { host: Some("amazon.com".to_string()), ..Default::default() }));
+        assert!(!query(EntryQuery { host: Some("zon.com".to_string()), ..Default::default() }));
+        assert!(query(EntryQuery { username: Some("roo".to_string()), ..Default::default() }));
+
+        let later = aws.created_at + Duration::from_secs(60);
+        assert!(query(EntryQuery { created_before: Some(later), modified_after: Some(aws.modified_at), ..Default::default() }));
+        assert!(!query(EntryQuery { created_after: Some(later), ..Default::default() }));
+        assert!(!query(EntryQuery { host: Some("amazon.com".to_string()), username: Some("admin".to_string()), ..Default::default() }));
+    }
+}
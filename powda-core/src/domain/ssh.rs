@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// Marks an entry as an SSH key and holds its public half. The private
+/// key is the entry's password, in unencrypted OpenSSH format, so it gets
+/// the vault's encryption, history and zeroizing like any other secret.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SshKeyInfo {
+    /// `ssh-ed25519 AAAA... comment`, as in a `.pub` file.
+    pub public_key: String,
+    /// `SHA256:...`, as `ssh-add -l` prints it.
+    pub fingerprint: String,
+    /// Ask before every signature `powda ssh-agent` makes with this key.
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+impl SshKeyInfo {
+    /// Algorithm name from the public key, e.g. `ssh-ed25519`.
+    pub fn algorithm(&self) -> &str {
+        self.public_key.split_whitespace().next().unwrap_or_default()
+    }
+}
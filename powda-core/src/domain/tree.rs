@@ -0,0 +1,123 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use super::value_objects::{EntryName, FOLDER_SEPARATOR};
+
+/// Entry names arranged by folder, for tree views. Folders sort before
+/// entries at each level; a name can be both an entry and a folder
+/// (`work` next to `work/vpn`).
+#[derive(Debug, Default)]
+pub struct NameTree {
+    folders: BTreeMap<String, NameTree>,
+    entries: BTreeSet<String>,
+}
+
+/// One visible line of a tree with some folders collapsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeRow {
+    /// Full name of the entry, or the folder path.
+    pub path: String,
+    /// Last segment of `path`.
+    pub label: String,
+    pub depth: usize,
+    pub kind: TreeRowKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeRowKind {
+    Folder { entries: usize, collapsed: bool },
+    Entry,
+}
+
+impl NameTree {
+    pub fn new<'a>(names: impl IntoIterator<Item = &'a EntryName>) -> Self {
+        let mut tree = Self::default();
+        for name in names {
+            let mut node = &mut tree;
+            if let Some(parent) = name.parent() {
+                for folder in parent.split(FOLDER_SEPARATOR) {
+                    node = node.folders.entry(folder.to_string()).or_default();
+                }
+            }
+            node.entries.insert(name.leaf().to_string());
+        }
+        tree
+    }
+
+    pub fn folders(&self) -> impl Iterator<Item = (&str, &NameTree)> {
+        self.folders.iter().map(|(name, tree)| (name.as_str(), tree))
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+
+    /// Entries in this folder and every folder below it.
+    pub fn len(&self) -> usize {
+        self.entries.len() + self.folders.values().map(NameTree::len).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Flatten into display rows, skipping the contents of folders whose
+    /// path is in `collapsed`.
+    pub fn rows(&self, collapsed: &HashSet<String>) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        self.push_rows("", 0, collapsed, &mut rows);
+        rows
+    }
+
+    fn push_rows(&self, prefix: &str, depth: usize, collapsed: &HashSet<String>, rows: &mut Vec<TreeRow>) {
+        let path = |label: &str| match prefix {
+            "" => label.to_string(),
+            _ => format!("{}{}{}", prefix, FOLDER_SEPARATOR, label),
+        };
+
+        for (label, folder) in &self.folders {
+            let folder_path = path(label);
+            let is_collapsed = collapsed.contains(&folder_path);
+            rows.push(TreeRow {
+                path: folder_path.clone(),
+                label: label.clone(),
+                depth,
+                kind: TreeRowKind::Folder { entries: folder.len(), collapsed: is_collapsed },
+            });
+            if !is_collapsed {
+                folder.push_rows(&folder_path, depth + 1, collapsed, rows);
+            }
+        }
+        for label in &self.entries {
+            rows.push(TreeRow { path: path(label), label: label.clone(), depth, kind: TreeRowKind::Entry });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_put_folders_first_and_hide_collapsed_contents() {
+        let names: Vec<EntryName> = ["mail", "work/vpn", "work/aws/prod-root", "work/aws/dev", "bank"]
+            .iter().map(|n| EntryName::new(n.to_string()).unwrap()).collect();
+        let tree = NameTree::new(&names);
+        assert_eq!(tree.len(), 5);
+
+        let paths = |rows: Vec<TreeRow>| rows.into_iter().map(|r| (r.depth, r.path)).collect::<Vec<_>>();
+        assert_eq!(paths(tree.rows(&HashSet::new())), [
+            (0, "work".to_string()),
+            (1, "work/aws".to_string()),
+            (2, "work/aws/dev".to_string()),
+            (2, "work/aws/prod-root".to_string()),
+            (1, "work/vpn".to_string()),
+            (0, "bank".to_string()),
+            (0, "mail".to_string()),
+        ]);
+
+        let collapsed = HashSet::from(["work/aws".to_string()]);
+        let rows = tree.rows(&collapsed);
+        assert_eq!(rows[1].kind, TreeRowKind::Folder { entries: 2, collapsed: true });
+        assert_eq!(rows[2].path, "work/vpn");
+    }
+}
@@ -1,27 +1,69 @@
 use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct EntryName(String);
 
+/// Separates folders in hierarchical names such as `work/aws/prod-root`.
+pub const FOLDER_SEPARATOR: char = '/';
+
 impl EntryName {
+    /// Longest name accepted, in bytes.
+    pub const MAX_LEN: usize = 100;
+
     pub fn new(name: String) -> Result<Self, String> {
         if name.is_empty() {
         return Err("Entry name cannot be empty".to_string());
         }
 
-        if name.len() > 100 {
+        if name.len() > Self::MAX_LEN {
             return Err("Entry name too long(max 100 chars)".to_string());
         }
 
+        if name.chars().any(char::is_control) {
+            return Err("Entry name cannot contain control characters".to_string());
+        }
+
+        for segment in name.split(FOLDER_SEPARATOR) {
+            if segment.trim().is_empty() {
+                return Err(format!("Entry name '{}' has an empty folder; use 'a/b', not '/a', 'a/' or 'a//b'", name));
+            }
+            if segment == "." || segment == ".." {
+                return Err(format!("Entry name '{}' cannot contain '.' or '..' folders", name));
+            }
+        }
+
         Ok(EntryName(name))
     }
 
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Folders and final name: `work/aws/root` gives `work`, `aws`, `root`.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.0.split(FOLDER_SEPARATOR)
+    }
+
+    /// The name without its folders.
+    pub fn leaf(&self) -> &str {
+        self.0.rsplit(FOLDER_SEPARATOR).next().unwrap_or(&self.0)
+    }
+
+    /// The folder holding this entry, if any.
+    pub fn parent(&self) -> Option<&str> {
+        self.0.rsplit_once(FOLDER_SEPARATOR).map(|(parent, _)| parent)
+    }
+
+    /// Whether this is `prefix` itself or lives somewhere below it.
+    pub fn is_under(&self, prefix: &str) -> bool {
+        let prefix = prefix.trim_end_matches(FOLDER_SEPARATOR);
+        prefix.is_empty() || self.0 == prefix || self.0.strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with(FOLDER_SEPARATOR))
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct Password(String);
 
 
@@ -37,3 +79,22 @@ impl Password {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hierarchical_names_are_validated_and_split() {
+        let name = EntryName::new("work/aws/prod-root".to_string()).unwrap();
+        assert_eq!(name.segments().collect::<Vec<_>>(), ["work", "aws", "prod-root"]);
+        assert_eq!(name.leaf(), "prod-root");
+        assert_eq!(name.parent(), Some("work/aws"));
+        assert!(name.is_under("work") && name.is_under("work/aws/") && name.is_under(""));
+        assert!(!name.is_under("wor") && !name.is_under("work/aws/prod"));
+
+        for bad in ["/work", "work/", "work//aws", "work/ /aws", "work/../aws", "a\nb"] {
+            assert!(EntryName::new(bad.to_string()).is_err(), "{:?} was accepted", bad);
+        }
+    }
+}
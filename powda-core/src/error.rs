@@ -9,6 +9,10 @@ pub enum Error {
     Io(std::io::Error),
     Serialization(serde_json::Error),
     Encryption(String),
+    Agent(String),
+    HeaderTampered,
+    Conflict(String),
+    InvalidInput(String),
 }
 
 impl fmt::Display for Error {
@@ -20,6 +24,38 @@ impl fmt::Display for Error {
             Error::Io(e) => write!(f, "IO error: {}", e),
             Error::Serialization(e) => write!(f, "Serialization error: {}", e),
             Error::Encryption(e) => write!(f, "Encryption error: {}", e),
+            Error::Agent(e) => write!(f, "Agent error: {}", e),
+            Error::HeaderTampered => write!(f, "Vault failed authentication - its header or contents were modified"),
+            Error::Conflict(e) => write!(f, "Vault changed on disk: {}", e),
+            Error::InvalidInput(e) => write!(f, "Invalid input: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// Stable identifier for scripts; unlike the message it never changes wording.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::NotInitialized => "not_initialized",
+            Error::AlreadyExists(_) => "already_exists",
+            Error::NotFound(_) => "not_found",
+            Error::Io(_) => "io",
+            Error::Serialization(_) => "serialization",
+            Error::Encryption(_) => "encryption",
+            Error::Agent(_) => "agent",
+            Error::HeaderTampered => "header_tampered",
+            Error::Conflict(_) => "conflict",
+            Error::InvalidInput(_) => "invalid_input",
         }
     }
 }
@@ -0,0 +1,67 @@
+use crate::domain::PasswordEntry;
+use crate::error::{Error, Result};
+use crate::timestamp::format_rfc3339;
+
+const HEADER: [&str; 9] = [
+    "name", "username", "password", "url", "notes", "tags", "otp", "created_at", "modified_at",
+];
+
+pub fn write(entries: &[PasswordEntry]) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(HEADER).map_err(to_io)?;
+
+    for entry in entries {
+        let metadata = &entry.metadata;
+        writer.write_record([
+            entry.name.as_str(),
+            metadata.username.as_deref().unwrap_or_default(),
+            entry.password.as_str(),
+            metadata.url.as_deref().unwrap_or_default(),
+            metadata.notes.as_deref().unwrap_or_default(),
+            &metadata.tags.join(";"),
+            &entry.otp.as_ref().map(|otp| otp.to_uri()).unwrap_or_default(),
+            &format_rfc3339(entry.created_at),
+            &format_rfc3339(entry.modified_at),
+        ]).map_err(to_io)?;
+    }
+
+    writer.into_inner().map_err(|e| Error::Io(e.into_error()))
+}
+
+fn to_io(e: csv::Error) -> Error {
+    Error::Io(e.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{EntryMetadata, EntryName, Password};
+    use crate::import::{CsvDialect, CsvImporter, Importer};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn generic_csv_import_reads_it_back() {
+        let mut entry = PasswordEntry::new(
+            EntryName::new("work/vpn".to_string()).unwrap(),
+            Password::new("p,w\"d".to_string()).unwrap(),
+        ).with_metadata(EntryMetadata {
+            username: Some("me".to_string()),
+            notes: Some("line one\nline two".to_string()),
+            tags: vec!["ops".to_string(), "remote".to_string()],
+            ..Default::default()
+        });
+        // Whole seconds, as the export writes them.
+        entry.created_at = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        entry.modified_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let csv = write(std::slice::from_ref(&entry)).unwrap();
+        let batch = CsvImporter::new(CsvDialect::Generic).parse(&csv).unwrap();
+        let read = &batch.entries[0];
+        assert_eq!(read.name, entry.name);
+        assert_eq!(read.password.as_str(), "p,w\"d");
+        assert_eq!(read.metadata.notes, entry.metadata.notes);
+        assert_eq!(read.metadata.tags, entry.metadata.tags);
+        assert_eq!(read.created_at, entry.created_at);
+        assert_eq!(read.modified_at, entry.modified_at);
+    }
+}
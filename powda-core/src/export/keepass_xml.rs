@@ -0,0 +1,147 @@
+use rand::RngCore;
+use rand_core::OsRng;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use crate::crypto::encoding;
+use crate::domain::PasswordEntry;
+use crate::timestamp::format_rfc3339;
+
+/// Entries grouped by the `/`-separated folders of their names.
+#[derive(Default)]
+struct Group<'a> {
+    groups: BTreeMap<&'a str, Group<'a>>,
+    entries: Vec<(&'a str, &'a PasswordEntry)>,
+}
+
+impl<'a> Group<'a> {
+    fn insert(&mut self, entry: &'a PasswordEntry) {
+        let mut parts: Vec<&str> = entry.name.as_str().split('/').filter(|p| !p.is_empty()).collect();
+        let title = parts.pop().unwrap_or(entry.name.as_str());
+        let group = parts.into_iter().fold(self, |group, part| group.groups.entry(part).or_default());
+        group.entries.push((title, entry));
+    }
+}
+
+pub fn write(entries: &[PasswordEntry]) -> String {
+    let mut root = Group::default();
+    for entry in entries {
+        root.insert(entry);
+    }
+
+    let mut xml = Xml::default();
+    xml.line("<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>");
+    xml.open("KeePassFile");
+    xml.open("Meta");
+    xml.text("Generator", "powda");
+    xml.close("Meta");
+    xml.open("Root");
+    write_group(&mut xml, "powda", &root);
+    xml.close("Root");
+    xml.close("KeePassFile");
+    xml.out
+}
+
+fn write_group(xml: &mut Xml, name: &str, group: &Group) {
+    xml.open("Group");
+    xml.text("UUID", &uuid());
+    xml.text("Name", name);
+    for (title, entry) in &group.entries {
+        write_entry(xml, title, entry);
+    }
+    for (name, child) in &group.groups {
+        write_group(xml, name, child);
+    }
+    xml.close("Group");
+}
+
+fn write_entry(xml: &mut Xml, title: &str, entry: &PasswordEntry) {
+    let uuid = uuid();
+    let fields = |xml: &mut Xml, password: &str, modified: SystemTime| {
+        xml.text("UUID", &uuid);
+        xml.text("Tags", &entry.metadata.tags.join(";"));
+        xml.open("Times");
+        xml.text("CreationTime", &format_rfc3339(entry.created_at));
+        xml.text("LastModificationTime", &format_rfc3339(modified));
+        xml.close("Times");
+        xml.string("Title", title, false);
+        xml.string("UserName", entry.metadata.username.as_deref().unwrap_or_default(), false);
+        xml.string("Password", password, true);
+        xml.string("URL", entry.metadata.url.as_deref().unwrap_or_default(), false);
+        xml.string("Notes", entry.metadata.notes.as_deref().unwrap_or_default(), false);
+        if let Some(otp) = &entry.otp {
+            xml.string("otp", &otp.to_uri(), true);
+        }
+    };
+
+    xml.open("Entry");
+    fields(xml, entry.password.as_str(), entry.modified_at);
+    if !entry.history.is_empty() {
+        xml.open("History");
+        // KeePass lists history oldest first; each version is a full copy
+        // of the entry as it was until it got replaced.
+        for version in entry.history.iter().rev() {
+            xml.open("Entry");
+            fields(xml, version.password.as_str(), version.replaced_at);
+            xml.close("Entry");
+        }
+        xml.close("History");
+    }
+    xml.close("Entry");
+}
+
+fn uuid() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    encoding::encode(&bytes)
+}
+
+#[derive(Default)]
+struct Xml {
+    out: String,
+    depth: usize,
+}
+
+impl Xml {
+    fn line(&mut self, line: &str) {
+        let _ = writeln!(self.out, "{:indent$}{}", "", line, indent = self.depth * 2);
+    }
+
+    fn open(&mut self, tag: &str) {
+        self.line(&format!("<{}>", tag));
+        self.depth += 1;
+    }
+
+    fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.line(&format!("</{}>", tag));
+    }
+
+    fn text(&mut self, tag: &str, value: &str) {
+        self.line(&format!("<{0}>{1}</{0}>", tag, escape(value)));
+    }
+
+    fn string(&mut self, key: &str, value: &str, protect: bool) {
+        let attr = if protect { " ProtectInMemory=\"True\"" } else { "" };
+        self.line(&format!(
+            "<String><Key>{}</Key><Value{}>{}</Value></String>", escape(key), attr, escape(value)
+        ));
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' => out.push_str("&#10;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
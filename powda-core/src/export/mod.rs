@@ -0,0 +1,130 @@
+mod csv;
+mod keepass_xml;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{CryptoManager, EncryptedVault, KdfParams};
+use crate::domain::PasswordEntry;
+use crate::error::{Error, Result};
+use crate::timestamp::format_rfc3339;
+
+/// Plaintext layouts `export` can write. Anything written in one of these
+/// is readable by whoever gets hold of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// powda's own JSON: every field, timestamps and history.
+    Json,
+    /// One row per entry with the columns `powda import --from generic-csv`
+    /// reads back. History does not fit a row and is left out.
+    Csv,
+    /// KeePass 2.x XML, importable by KeePass and KeePassXC. Names
+    /// containing `/` become nested groups.
+    KeepassXml,
+}
+
+/// Serialize `entries` in a plaintext `format`.
+pub fn export(entries: &[PasswordEntry], format: ExportFormat) -> Result<Vec<u8>> {
+    match format {
+        ExportFormat::Json => to_json(entries),
+        ExportFormat::Csv => csv::write(entries),
+        ExportFormat::KeepassXml => Ok(keepass_xml::write(entries).into_bytes()),
+    }
+}
+
+const ARCHIVE_FORMAT: &str = "powda-archive";
+
+/// Encrypted export: the same envelope as a vault file, keyed by its own
+/// password and KDF so it can be opened without the vault it came from.
+#[derive(Serialize, Deserialize)]
+struct Archive {
+    format: String,
+    #[serde(flatten)]
+    vault: EncryptedVault,
+}
+
+/// Encrypt `entries` under `password` for `powda import --from archive`.
+pub fn seal_archive(entries: &[PasswordEntry], password: &str, kdf: KdfParams) -> Result<Vec<u8>> {
+    let data = serde_json::to_vec(entries)?;
+    let vault = CryptoManager::new().create_vault(password, &data, kdf)?;
+    let archive = Archive { format: ARCHIVE_FORMAT.to_string(), vault };
+    Ok(serde_json::to_vec_pretty(&archive)?)
+}
+
+pub fn open_archive(data: &[u8], password: &str) -> Result<Vec<PasswordEntry>> {
+    let archive: Archive = serde_json::from_slice(data)
+        .ok()
+        .filter(|archive: &Archive| archive.format == ARCHIVE_FORMAT)
+        .ok_or_else(|| Error::InvalidInput("Not a powda export archive".to_string()))?;
+    let data = CryptoManager::new().open_vault(password, &archive.vault)
+        .map_err(|_| Error::Encryption("Wrong archive password or damaged archive".to_string()))?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    version: u32,
+    entries: Vec<JsonEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonEntry<'a> {
+    name: &'a str,
+    password: &'a str,
+    username: Option<&'a str>,
+    url: Option<&'a str>,
+    notes: Option<&'a str>,
+    tags: &'a [String],
+    otp: Option<String>,
+    created_at: String,
+    modified_at: String,
+    history: Vec<JsonVersion<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonVersion<'a> {
+    password: &'a str,
+    replaced_at: String,
+}
+
+fn to_json(entries: &[PasswordEntry]) -> Result<Vec<u8>> {
+    let entries = entries.iter().map(|entry| JsonEntry {
+        name: entry.name.as_str(),
+        password: entry.password.as_str(),
+        username: entry.metadata.username.as_deref(),
+        url: entry.metadata.url.as_deref(),
+        notes: entry.metadata.notes.as_deref(),
+        tags: &entry.metadata.tags,
+        otp: entry.otp.as_ref().map(|otp| otp.to_uri()),
+        created_at: format_rfc3339(entry.created_at),
+        modified_at: format_rfc3339(entry.modified_at),
+        history: entry.history.iter().map(|version| JsonVersion {
+            password: version.password.as_str(),
+            replaced_at: format_rfc3339(version.replaced_at),
+        }).collect(),
+    }).collect();
+
+    Ok(serde_json::to_vec_pretty(&JsonExport { version: 1, entries })?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{EntryName, Password};
+
+    #[test]
+    fn archive_opens_only_with_its_password() {
+        let mut entry = PasswordEntry::new(
+            EntryName::new("mail".to_string()).unwrap(),
+            Password::new("first".to_string()).unwrap(),
+        );
+        entry.update_password(Password::new("second".to_string()).unwrap());
+
+        let archive = seal_archive(&[entry], "archive pass", KdfParams::new(8, 1, 1).unwrap()).unwrap();
+        let entries = open_archive(&archive, "archive pass").unwrap();
+        assert_eq!(entries[0].password.as_str(), "second");
+        assert_eq!(entries[0].history[0].password.as_str(), "first");
+
+        assert!(open_archive(&archive, "wrong").is_err());
+        assert!(matches!(open_archive(b"{}", "archive pass"), Err(Error::InvalidInput(_))));
+    }
+}
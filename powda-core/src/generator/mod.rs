@@ -0,0 +1,56 @@
+mod charset;
+mod passphrase;
+mod pronounceable;
+
+pub use charset::{CharClass, CharsetPolicy};
+pub use passphrase::{PassphrasePolicy, WORDLIST_LEN};
+pub use pronounceable::PronounceablePolicy;
+
+use rand::Rng;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::Password;
+use crate::error::{Error, Result};
+
+/// What kind of secret to generate and how.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "style", rename_all = "snake_case")]
+pub enum Recipe {
+    Charset(CharsetPolicy),
+    Pronounceable(PronounceablePolicy),
+    Passphrase(PassphrasePolicy),
+}
+
+impl Default for Recipe {
+    fn default() -> Self {
+        Recipe::Charset(CharsetPolicy::default())
+    }
+}
+
+impl Recipe {
+    pub fn generate(&self) -> Result<Password> {
+        match self {
+            Recipe::Charset(policy) => policy.generate(),
+            Recipe::Pronounceable(policy) => policy.generate(),
+            Recipe::Passphrase(policy) => policy.generate(),
+        }
+    }
+
+    /// Approximate strength of one generated secret, assuming the recipe is known.
+    pub fn entropy_bits(&self) -> Result<f64> {
+        match self {
+            Recipe::Charset(policy) => policy.entropy_bits(),
+            Recipe::Pronounceable(policy) => policy.entropy_bits(),
+            Recipe::Passphrase(policy) => policy.entropy_bits(),
+        }
+    }
+}
+
+fn pick<T: Copy>(items: &[T]) -> T {
+    items[OsRng.gen_range(0..items.len())]
+}
+
+fn into_password(secret: String) -> Result<Password> {
+    Password::new(secret).map_err(Error::InvalidInput)
+}
@@ -0,0 +1,86 @@
+use std::sync::LazyLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::Password;
+use crate::error::{Error, Result};
+
+use super::{into_password, pick};
+
+pub const WORDLIST_LEN: usize = 7776;
+const MAX_WORDS: usize = 64;
+
+/// The EFF large diceware list (CC BY 3.0, Electronic Frontier Foundation),
+/// one word per five-dice roll.
+static WORDS: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| include_str!("eff_large_wordlist.txt").lines().collect());
+
+/// Diceware-style passphrase of whole words.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PassphrasePolicy {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: bool,
+}
+
+impl Default for PassphrasePolicy {
+    fn default() -> Self {
+        Self { words: 6, separator: "-".to_string(), capitalize: false }
+    }
+}
+
+impl PassphrasePolicy {
+    fn validate(&self) -> Result<()> {
+        if self.words == 0 || self.words > MAX_WORDS {
+            return Err(Error::InvalidInput(format!("Word count must be between 1 and {}", MAX_WORDS)));
+        }
+        Ok(())
+    }
+
+    pub fn generate(&self) -> Result<Password> {
+        self.validate()?;
+
+        let words: Vec<String> = (0..self.words)
+            .map(|_| {
+                let word = pick(&WORDS);
+                if self.capitalize {
+                    // The list is plain lowercase ASCII.
+                    word[..1].to_ascii_uppercase() + &word[1..]
+                } else {
+                    word.to_string()
+                }
+            })
+            .collect();
+
+        into_password(words.join(&self.separator))
+    }
+
+    pub fn entropy_bits(&self) -> Result<f64> {
+        self.validate()?;
+        Ok(self.words as f64 * (WORDS.len() as f64).log2())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wordlist_is_complete_and_unique() {
+        let mut words = WORDS.clone();
+        words.sort_unstable();
+        words.dedup();
+        assert_eq!(words.len(), WORDLIST_LEN);
+    }
+
+    #[test]
+    fn joins_words_with_separator() {
+        let policy = PassphrasePolicy { words: 5, separator: " ".to_string(), capitalize: true };
+        let password = policy.generate().unwrap();
+        let words: Vec<&str> = password.as_str().split(' ').collect();
+
+        assert_eq!(words.len(), 5);
+        assert!(words.iter().all(|w| w.starts_with(|c: char| c.is_ascii_uppercase())));
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::Password;
+use crate::error::{Error, Result};
+
+use super::charset::MAX_LENGTH;
+use super::{into_password, pick};
+
+const CONSONANTS: &[char] = &[
+    'b', 'c', 'd', 'f', 'g', 'h', 'j', 'k', 'l', 'm', 'n', 'p', 'r', 's', 't', 'v', 'w', 'z',
+];
+const VOWELS: &[char] = &['a', 'e', 'i', 'o', 'u'];
+const DIGITS: &[char] = &['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+
+/// Alternating consonants and vowels, optionally capitalised and followed
+/// by digits for sites that insist on them. `length` includes the digits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PronounceablePolicy {
+    pub length: usize,
+    pub capitalize: bool,
+    pub digits: usize,
+}
+
+impl Default for PronounceablePolicy {
+    fn default() -> Self {
+        Self { length: 16, capitalize: false, digits: 0 }
+    }
+}
+
+impl PronounceablePolicy {
+    fn letters(&self) -> Result<usize> {
+        if self.length == 0 || self.length > MAX_LENGTH {
+            return Err(Error::InvalidInput(format!("Length must be between 1 and {}", MAX_LENGTH)));
+        }
+        if self.digits >= self.length {
+            return Err(Error::InvalidInput("Digits must leave room for at least one letter".to_string()));
+        }
+        Ok(self.length - self.digits)
+    }
+
+    pub fn generate(&self) -> Result<Password> {
+        let letters = self.letters()?;
+
+        let mut secret = String::with_capacity(self.length);
+        for i in 0..letters {
+            let c = pick(if i % 2 == 0 { CONSONANTS } else { VOWELS });
+            if i == 0 && self.capitalize {
+                secret.push(c.to_ascii_uppercase());
+            } else {
+                secret.push(c);
+            }
+        }
+        secret.extend((0..self.digits).map(|_| pick(DIGITS)));
+
+        into_password(secret)
+    }
+
+    pub fn entropy_bits(&self) -> Result<f64> {
+        let letters = self.letters()?;
+        let consonants = letters.div_ceil(2) as f64;
+        let vowels = (letters / 2) as f64;
+        Ok(consonants * (CONSONANTS.len() as f64).log2()
+            + vowels * (VOWELS.len() as f64).log2()
+            + self.digits as f64 * (DIGITS.len() as f64).log2())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alternates_consonants_and_vowels() {
+        let policy = PronounceablePolicy { length: 10, capitalize: true, digits: 2 };
+        let password = policy.generate().unwrap();
+        let chars: Vec<char> = password.as_str().chars().collect();
+
+        assert_eq!(chars.len(), 10);
+        assert!(chars[0].is_ascii_uppercase());
+        for (i, c) in chars[..8].iter().enumerate() {
+            let c = c.to_ascii_lowercase();
+            assert_eq!(VOWELS.contains(&c), i % 2 == 1, "{}", password.as_str());
+        }
+        assert!(chars[8..].iter().all(char::is_ascii_digit));
+    }
+}
@@ -0,0 +1,23 @@
+use super::{ImportBatch, Importer};
+use crate::error::Result;
+use crate::export;
+use zeroize::Zeroizing;
+
+/// Encrypted archive written by `powda export`. Entries come back exactly
+/// as exported, history and timestamps included.
+pub struct ArchiveImporter {
+    password: Zeroizing<String>,
+}
+
+impl ArchiveImporter {
+    pub fn new(password: &str) -> Self {
+        Self { password: Zeroizing::new(password.to_string()) }
+    }
+}
+
+impl Importer for ArchiveImporter {
+    fn parse(&self, data: &[u8]) -> Result<ImportBatch> {
+        let entries = export::open_archive(data, &self.password)?;
+        Ok(ImportBatch { entries, ..ImportBatch::default() })
+    }
+}
@@ -0,0 +1,141 @@
+use super::{finish, Draft, ImportBatch, Importer};
+use crate::error::{Error, Result};
+use crate::timestamp;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Unencrypted Bitwarden JSON export (`bw export --format json`).
+pub struct BitwardenImporter;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    folders: Vec<Folder>,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Folder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(rename = "type")]
+    kind: u8,
+    name: String,
+    folder_id: Option<String>,
+    notes: Option<String>,
+    login: Option<Login>,
+    creation_date: Option<String>,
+    revision_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+    #[serde(default)]
+    uris: Vec<Uri>,
+}
+
+#[derive(Deserialize)]
+struct Uri {
+    uri: Option<String>,
+}
+
+const LOGIN: u8 = 1;
+
+impl Importer for BitwardenImporter {
+    fn parse(&self, data: &[u8]) -> Result<ImportBatch> {
+        let export: Export = serde_json::from_slice(data)
+            .map_err(|e| Error::InvalidInput(format!("Not a Bitwarden JSON export: {}", e)))?;
+        if export.encrypted {
+            return Err(Error::InvalidInput(
+                "Encrypted Bitwarden exports are not supported, export as unencrypted JSON".to_string(),
+            ));
+        }
+
+        let folders: HashMap<String, String> = export.folders.into_iter()
+            .map(|folder| (folder.id, folder.name))
+            .collect();
+
+        let mut drafts = Vec::new();
+        let mut unsupported = Vec::new();
+        for item in export.items {
+            let Some(login) = item.login.filter(|_| item.kind == LOGIN) else {
+                unsupported.push(item.name);
+                continue;
+            };
+
+            let folder = item.folder_id.and_then(|id| folders.get(&id).cloned());
+            drafts.push(Draft {
+                folder: folder.map(|f| f.split('/').map(str::to_string).collect()).unwrap_or_default(),
+                title: item.name,
+                username: login.username,
+                password: login.password,
+                url: login.uris.into_iter().find_map(|u| u.uri),
+                notes: item.notes,
+                tags: Vec::new(),
+                otp: login.totp,
+                created_at: item.creation_date.as_deref().and_then(timestamp::parse_rfc3339),
+                modified_at: item.revision_date.as_deref().and_then(timestamp::parse_rfc3339),
+            });
+        }
+        finish(drafts, unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logins_are_imported_with_their_folder() {
+        let export = r#"{
+            "encrypted": false,
+            "folders": [{"id": "f1", "name": "Work"}],
+            "items": [
+                {"type": 1, "name": "Jira", "folderId": "f1", "notes": null,
+                 "creationDate": "2023-11-14T22:13:20.000Z",
+                 "login": {"username": "me", "password": "pw", "totp": "JBSWY3DPEHPK3PXP",
+                           "uris": [{"match": null, "uri": "https://jira.example.com"}]}},
+                {"type": 3, "name": "Visa", "folderId": null, "card": {}}
+            ]
+        }"#;
+        let batch = BitwardenImporter.parse(export.as_bytes()).unwrap();
+        assert_eq!(batch.unsupported, ["Visa"]);
+
+        let jira = &batch.entries[0];
+        assert_eq!(jira.name.as_str(), "Work/Jira");
+        assert_eq!(jira.metadata.url.as_deref(), Some("https://jira.example.com"));
+        assert!(jira.otp.is_some());
+        assert_eq!(jira.created_at, super::super::from_unix_secs(1_700_000_000).unwrap());
+        assert!(batch.invalid_otp.is_empty());
+    }
+
+    #[test]
+    fn malformed_totp_is_reported_and_the_login_kept() {
+        let export = r#"{
+            "encrypted": false,
+            "items": [
+                {"type": 1, "name": "Mail", "login": {"username": "me", "password": "pw", "totp": "not base32!"}},
+                {"type": 1, "name": "Bank", "login": {"password": "pw", "totp": "otpauth://totp/Bank?digits=6"}}
+            ]
+        }"#;
+        let batch = BitwardenImporter.parse(export.as_bytes()).unwrap();
+
+        assert_eq!(batch.entries.len(), 2);
+        assert!(batch.entries.iter().all(|entry| entry.otp.is_none()));
+        let names: Vec<_> = batch.invalid_otp.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["Mail", "Bank"]);
+        assert!(batch.invalid_otp[1].1.contains("missing secret"), "{:?}", batch.invalid_otp);
+    }
+}
@@ -0,0 +1,158 @@
+use super::{finish, from_unix_secs, Draft, ImportBatch, Importer};
+use crate::error::{Error, Result};
+use crate::timestamp::parse_rfc3339;
+
+/// Column layout of a CSV export. All dialects match headers by name, so
+/// column order and extra columns do not matter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvDialect {
+    /// `name,url,username,password,note`
+    Chrome,
+    /// `url,username,password,httpRealm,formActionOrigin,guid,timeCreated,timeLastUsed,timePasswordChanged`
+    Firefox,
+    /// Any of `name`/`title`, `username`/`login`, `password`, `url`, `notes`,
+    /// `tags` (comma or semicolon separated), `otp`/`totp`, `folder` and
+    /// RFC 3339 `created_at`/`modified_at`, as `powda export` writes them.
+    Generic,
+}
+
+pub struct CsvImporter {
+    dialect: CsvDialect,
+}
+
+impl CsvImporter {
+    pub fn new(dialect: CsvDialect) -> Self {
+        Self { dialect }
+    }
+
+    fn aliases(&self, field: Field) -> &'static [&'static str] {
+        match (self.dialect, field) {
+            (CsvDialect::Chrome, Field::Title) => &["name"],
+            (CsvDialect::Chrome, Field::Notes) => &["note"],
+            (CsvDialect::Firefox, Field::Title) => &[],
+            (CsvDialect::Firefox, Field::Notes) => &[],
+            (CsvDialect::Chrome | CsvDialect::Firefox, Field::Tags | Field::Otp | Field::Folder) => &[],
+            (_, Field::Username) => &["username", "login", "login_username", "user"],
+            (_, Field::Password) => &["password", "login_password"],
+            (_, Field::Url) => &["url", "uri", "login_uri", "website"],
+            (CsvDialect::Generic, Field::Title) => &["name", "title"],
+            (CsvDialect::Generic, Field::Notes) => &["notes", "note", "comments", "extra"],
+            (CsvDialect::Generic, Field::Tags) => &["tags", "labels"],
+            (CsvDialect::Generic, Field::Otp) => &["otp", "totp", "login_totp"],
+            (CsvDialect::Generic, Field::Folder) => &["folder", "group", "grouping"],
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Title,
+    Username,
+    Password,
+    Url,
+    Notes,
+    Tags,
+    Otp,
+    Folder,
+}
+
+impl Importer for CsvImporter {
+    fn parse(&self, data: &[u8]) -> Result<ImportBatch> {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(data);
+        let headers: Vec<String> = reader.headers()
+            .map_err(invalid)?
+            .iter()
+            .map(|h| h.trim().to_ascii_lowercase())
+            .collect();
+
+        let column = |field: Field| {
+            self.aliases(field).iter().find_map(|alias| headers.iter().position(|h| h == alias))
+        };
+        if column(Field::Password).is_none() {
+            return Err(Error::InvalidInput("CSV has no password column".to_string()));
+        }
+        let columns = [
+            Field::Title, Field::Username, Field::Password, Field::Url,
+            Field::Notes, Field::Tags, Field::Otp, Field::Folder,
+        ].map(column);
+        let position = |name: &str| headers.iter().position(|h| h == name);
+        // Firefox counts milliseconds since the epoch; `powda export` writes RFC 3339.
+        let (created_ms, changed_ms) = (position("timecreated"), position("timepasswordchanged"));
+        let (created, modified) = match self.dialect {
+            CsvDialect::Generic => (position("created_at"), position("modified_at")),
+            _ => (None, None),
+        };
+
+        let mut drafts = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(invalid)?;
+            let get = |index: Option<usize>| {
+                index.and_then(|i| record.get(i)).map(str::to_string).filter(|v| !v.is_empty())
+            };
+            let [title, username, password, url, notes, tags, otp, folder] = columns.map(get);
+
+            drafts.push(Draft {
+                folder: folder.map(|f| f.split('/').map(str::to_string).collect()).unwrap_or_default(),
+                title: title.unwrap_or_default(),
+                username,
+                password,
+                url,
+                notes,
+                tags: tags.map(|t| split_tags(&t)).unwrap_or_default(),
+                otp,
+                created_at: get(created).as_deref().and_then(parse_rfc3339)
+                    .or_else(|| get(created_ms).as_deref().and_then(millis)),
+                modified_at: get(modified).as_deref().and_then(parse_rfc3339)
+                    .or_else(|| get(changed_ms).as_deref().and_then(millis)),
+            });
+        }
+        finish(drafts, Vec::new())
+    }
+}
+
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split([',', ';'])
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Firefox stores times as milliseconds since the epoch.
+fn millis(value: &str) -> Option<std::time::SystemTime> {
+    from_unix_secs(value.trim().parse::<i64>().ok()? / 1000)
+}
+
+fn invalid(e: csv::Error) -> Error {
+    Error::InvalidInput(format!("Malformed CSV: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dialects_map_their_columns() {
+        let chrome = "name,url,username,password,note\n\
+                      GitHub,https://github.com/login,octo,hunter2,work account\n\
+                      ,https://example.org,,,\n";
+        let batch = CsvImporter::new(CsvDialect::Chrome).parse(chrome.as_bytes()).unwrap();
+        assert_eq!(batch.entries.len(), 1);
+        assert_eq!(batch.unsupported, ["example.org"]);
+        let github = &batch.entries[0];
+        assert_eq!(github.name.as_str(), "GitHub");
+        assert_eq!(github.metadata.username.as_deref(), Some("octo"));
+        assert_eq!(github.metadata.notes.as_deref(), Some("work account"));
+
+        let firefox = "\"url\",\"username\",\"password\",\"httpRealm\",\"timeCreated\"\n\
+                       \"https://accounts.firefox.com\",\"me\",\"pw\",,\"1700000000000\"\n";
+        let batch = CsvImporter::new(CsvDialect::Firefox).parse(firefox.as_bytes()).unwrap();
+        let entry = &batch.entries[0];
+        assert_eq!(entry.name.as_str(), "accounts.firefox.com");
+        assert_eq!(entry.created_at, from_unix_secs(1_700_000_000).unwrap());
+
+        let generic = "Title,Password,Folder,Tags\nVPN,pw,Work/Infra,\"ops; remote\"\n";
+        let batch = CsvImporter::new(CsvDialect::Generic).parse(generic.as_bytes()).unwrap();
+        assert_eq!(batch.entries[0].name.as_str(), "Work/Infra/VPN");
+        assert_eq!(batch.entries[0].metadata.tags, ["ops", "remote"]);
+    }
+}
@@ -0,0 +1,89 @@
+use super::{finish, from_unix_secs, Draft, ImportBatch, Importer};
+use crate::error::{Error, Result};
+use keepass::db::{fields, GroupId, GroupRef};
+use keepass::{Database, DatabaseKey};
+use zeroize::Zeroizing;
+
+/// KeePass / KeePassXC database (KDBX 3.1 and 4), opened with its
+/// master password. Group paths below the root become the folder.
+pub struct KdbxImporter {
+    password: Zeroizing<String>,
+}
+
+impl KdbxImporter {
+    pub fn new(password: &str) -> Self {
+        Self { password: Zeroizing::new(password.to_string()) }
+    }
+}
+
+impl Importer for KdbxImporter {
+    fn parse(&self, data: &[u8]) -> Result<ImportBatch> {
+        let key = DatabaseKey::new().with_password(&self.password);
+        let db = Database::parse(data, key)
+            .map_err(|e| Error::Encryption(format!("Cannot open KeePass database: {}", e)))?;
+
+        let mut drafts = Vec::new();
+        collect(&db.root(), &mut Vec::new(), db.meta.recyclebin_uuid.map(GroupId::from), &mut drafts);
+        finish(drafts, Vec::new())
+    }
+}
+
+fn collect(group: &GroupRef<'_>, path: &mut Vec<String>, recycle_bin: Option<GroupId>, drafts: &mut Vec<Draft>) {
+    drafts.extend(group.entries().map(|entry| draft(path, &entry)));
+    for child in group.groups() {
+        if Some(child.id()) == recycle_bin {
+            continue;
+        }
+        path.push(child.name.clone());
+        collect(&child, path, recycle_bin, drafts);
+        path.pop();
+    }
+}
+
+fn draft(folder: &[String], entry: &keepass::db::Entry) -> Draft {
+    let field = |key: &str| entry.get(key).map(str::to_string);
+
+    Draft {
+        folder: folder.to_vec(),
+        title: field(fields::TITLE).unwrap_or_default(),
+        username: field(fields::USERNAME),
+        password: field(fields::PASSWORD),
+        url: field(fields::URL),
+        notes: field(fields::NOTES),
+        tags: entry.tags.clone(),
+        otp: field(fields::OTP),
+        created_at: entry.times.creation.and_then(|t| from_unix_secs(t.and_utc().timestamp())),
+        modified_at: entry.times.last_modification.and_then(|t| from_unix_secs(t.and_utc().timestamp())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keepass::config::KdfConfig;
+
+    #[test]
+    fn groups_become_folders() {
+        let mut db = Database::new();
+        // The default Argon2 settings take seconds; a test only needs a key.
+        db.config.kdf_config = KdfConfig::Aes { rounds: 10 };
+        db.root_mut().add_group().edit(|group| group.name = "Servers".to_string())
+            .add_entry().edit(|entry| {
+                entry.set_unprotected(fields::TITLE, "db01");
+                entry.set_unprotected(fields::USERNAME, "root");
+                entry.set_protected(fields::PASSWORD, "toor");
+            });
+        db.root_mut().add_entry().edit(|entry| entry.set_unprotected(fields::TITLE, "no password"));
+
+        let mut kdbx = Vec::new();
+        db.save(&mut kdbx, DatabaseKey::new().with_password("kdbx pass")).unwrap();
+
+        let batch = KdbxImporter::new("kdbx pass").parse(&kdbx).unwrap();
+        assert_eq!(batch.entries.len(), 1);
+        assert_eq!(batch.entries[0].name.as_str(), "Servers/db01");
+        assert_eq!(batch.entries[0].password.as_str(), "toor");
+        assert_eq!(batch.unsupported, ["no password"]);
+
+        assert!(KdbxImporter::new("wrong").parse(&kdbx).is_err());
+    }
+}
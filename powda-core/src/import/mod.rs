@@ -0,0 +1,263 @@
+mod archive;
+mod bitwarden;
+mod csv;
+mod kdbx;
+mod onepassword;
+
+pub use self::csv::{CsvDialect, CsvImporter};
+pub use archive::ArchiveImporter;
+pub use bitwarden::BitwardenImporter;
+pub use kdbx::KdbxImporter;
+pub use onepassword::OnePuxImporter;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::domain::{url_host, EntryMetadata, EntryName, OtpSecret, Password, PasswordEntry};
+use crate::error::{Error, Result};
+
+/// Turns another password manager's export into vault entries.
+pub trait Importer {
+    fn parse(&self, data: &[u8]) -> Result<ImportBatch>;
+}
+
+/// Entries read from an export, plus the items that had no equivalent in
+/// powda (cards, identities, notes without a password).
+#[derive(Debug, Default)]
+pub struct ImportBatch {
+    pub entries: Vec<PasswordEntry>,
+    pub unsupported: Vec<String>,
+    /// `(entry name, reason)` for entries imported without their one-time
+    /// code because the seed could not be parsed.
+    pub invalid_otp: Vec<(String, String)>,
+}
+
+/// What to do when an imported entry has the name of one already stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionStrategy {
+    #[default]
+    Skip,
+    /// Replace password and metadata; the old password goes into history.
+    Overwrite,
+    /// Store the import under the first free `name (N)`.
+    Rename,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub added: Vec<String>,
+    pub overwritten: Vec<String>,
+    /// `(imported name, name it was stored under)`
+    pub renamed: Vec<(String, String)>,
+    pub skipped: Vec<String>,
+}
+
+/// Merge `entries` into `data`, resolving name clashes with `strategy`.
+/// Entries in the same batch collide with each other like stored ones.
+pub fn merge(
+    data: &mut HashMap<String, PasswordEntry>,
+    entries: Vec<PasswordEntry>,
+    strategy: CollisionStrategy,
+) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+
+    for mut entry in entries {
+        let name = entry.name.as_str().to_string();
+        let Some(existing) = data.get_mut(&name) else {
+            report.added.push(name.clone());
+            data.insert(name, entry);
+            continue;
+        };
+
+        match strategy {
+            CollisionStrategy::Skip => report.skipped.push(name),
+            CollisionStrategy::Overwrite => {
+                existing.update_password(entry.password.clone());
+                existing.update_metadata(entry.metadata.clone());
+                if entry.otp.is_some() {
+                    existing.update_otp(entry.otp.take());
+                }
+                report.overwritten.push(name);
+            }
+            CollisionStrategy::Rename => {
+                let renamed = (2..)
+                    .map(|n| with_suffix(&name, &format!(" ({})", n)))
+                    .find(|candidate| !data.contains_key(candidate))
+                    .expect("unbounded range");
+                entry.name = EntryName::new(renamed.clone()).map_err(Error::InvalidInput)?;
+                report.renamed.push((name, renamed.clone()));
+                data.insert(renamed, entry);
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Source-neutral fields of one imported item, before it becomes an entry.
+#[derive(Debug, Default)]
+struct Draft {
+    folder: Vec<String>,
+    title: String,
+    username: Option<String>,
+    password: Option<String>,
+    url: Option<String>,
+    notes: Option<String>,
+    tags: Vec<String>,
+    otp: Option<String>,
+    created_at: Option<SystemTime>,
+    modified_at: Option<SystemTime>,
+}
+
+impl Draft {
+    /// Folder path and title joined with `/`, falling back to the URL host
+    /// or username for untitled items. Empty and `.`/`..` segments are
+    /// dropped so the result is always a valid entry name.
+    fn name(&self) -> String {
+        let title = Some(self.title.trim())
+            .filter(|t| !t.is_empty())
+            .or_else(|| self.url.as_deref().and_then(url_host))
+            .or(self.username.as_deref())
+            .unwrap_or("untitled");
+
+        let mut parts: Vec<String> = self.folder.iter().map(String::as_str).chain([title])
+            .flat_map(|part| part.split('/'))
+            .map(|part| part.chars().filter(|c| !c.is_control()).collect::<String>().trim().to_string())
+            .filter(|part| !part.is_empty() && part != "." && part != "..")
+            .collect();
+        if parts.is_empty() {
+            parts.push("untitled".to_string());
+        }
+
+        truncate(&parts.join("/"), EntryName::MAX_LEN).to_string()
+    }
+}
+
+/// `name` with `suffix` appended, cutting `name` short where needed so
+/// the result still fits in an entry name.
+fn with_suffix(name: &str, suffix: &str) -> String {
+    let base = truncate(name, EntryName::MAX_LEN.saturating_sub(suffix.len()));
+    format!("{}{}", base, suffix)
+}
+
+/// The longest prefix of `name` within `max` bytes that ends on a
+/// character boundary, without a trailing `/` or space.
+fn truncate(name: &str, max: usize) -> &str {
+    let mut end = name.len().min(max);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    name[..end].trim_end_matches(['/', ' '])
+}
+
+/// Turn drafts into entries; drafts without a password are reported as
+/// unsupported rather than failing the whole import.
+fn finish(drafts: Vec<Draft>, mut unsupported: Vec<String>) -> Result<ImportBatch> {
+    let mut entries = Vec::with_capacity(drafts.len());
+    let mut invalid_otp = Vec::new();
+
+    for draft in drafts {
+        let name = draft.name();
+        let Some(password) = draft.password.filter(|p| !p.is_empty()) else {
+            unsupported.push(name);
+            continue;
+        };
+
+        let mut entry = PasswordEntry::new(
+            EntryName::new(name).map_err(Error::InvalidInput)?,
+            Password::new(password).map_err(Error::InvalidInput)?,
+        )
+        .with_metadata(EntryMetadata {
+            url: non_empty(draft.url),
+            username: non_empty(draft.username),
+            notes: non_empty(draft.notes),
+            tags: draft.tags,
+        });
+        // A seed we cannot parse is reported instead of failing the import.
+        match draft.otp.as_deref().map(OtpSecret::parse) {
+            Some(Ok(otp)) => entry.otp = Some(otp),
+            Some(Err(Error::InvalidInput(why))) => invalid_otp.push((entry.name.as_str().to_string(), why)),
+            Some(Err(e)) => invalid_otp.push((entry.name.as_str().to_string(), e.to_string())),
+            None => {}
+        }
+        if let Some(created) = draft.created_at {
+            entry.created_at = created;
+        }
+        if let Some(modified) = draft.modified_at.or(draft.created_at) {
+            entry.modified_at = modified;
+        }
+        entries.push(entry);
+    }
+
+    Ok(ImportBatch { entries, unsupported, invalid_otp })
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+fn from_unix_secs(secs: i64) -> Option<SystemTime> {
+    u64::try_from(secs).ok().map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, password: &str) -> PasswordEntry {
+        PasswordEntry::new(EntryName::new(name.to_string()).unwrap(), Password::new(password.to_string()).unwrap())
+    }
+
+    fn vault() -> HashMap<String, PasswordEntry> {
+        HashMap::from([("github".to_string(), entry("github", "old"))])
+    }
+
+    #[test]
+    fn collision_strategies() {
+        let mut data = vault();
+        let report = merge(&mut data, vec![entry("github", "new"), entry("gitlab", "x")], CollisionStrategy::Skip).unwrap();
+        assert_eq!((report.added, report.skipped), (vec!["gitlab".to_string()], vec!["github".to_string()]));
+        assert_eq!(data["github"].password.as_str(), "old");
+
+        let mut data = vault();
+        merge(&mut data, vec![entry("github", "new")], CollisionStrategy::Overwrite).unwrap();
+        assert_eq!(data["github"].password.as_str(), "new");
+        assert_eq!(data["github"].history[0].password.as_str(), "old");
+
+        let mut data = vault();
+        let report = merge(&mut data, vec![entry("github", "a"), entry("github", "b")], CollisionStrategy::Rename).unwrap();
+        assert_eq!(report.renamed, [
+            ("github".to_string(), "github (2)".to_string()),
+            ("github".to_string(), "github (3)".to_string()),
+        ]);
+        assert_eq!(data["github (3)"].password.as_str(), "b");
+    }
+
+    #[test]
+    fn untitled_items_are_named_after_their_site() {
+        let draft = Draft { url: Some("https://user@login.example.com:8443/path".to_string()), ..Default::default() };
+        assert_eq!(draft.name(), "login.example.com");
+
+        let draft = Draft { folder: vec!["Work".to_string(), " ".to_string()], title: "VPN".to_string(), ..Default::default() };
+        assert_eq!(draft.name(), "Work/VPN");
+    }
+
+    #[test]
+    fn renamed_long_names_still_fit() {
+        // 99 bytes, with a two-byte character where the cut falls.
+        let long = format!("{}écc", "a".repeat(95));
+        assert_eq!(long.len(), 99);
+        let mut data = HashMap::from([(long.clone(), entry(&long, "old"))]);
+
+        let report = merge(&mut data, vec![entry(&long, "a"), entry(&long, "b")], CollisionStrategy::Rename).unwrap();
+        let stored: Vec<&str> = report.renamed.iter().map(|(_, to)| to.as_str()).collect();
+        assert_eq!(stored, [format!("{} (2)", "a".repeat(95)), format!("{} (3)", "a".repeat(95))]);
+        assert!(stored.iter().all(|name| name.len() <= EntryName::MAX_LEN));
+
+        let full = "b".repeat(EntryName::MAX_LEN);
+        let mut data = HashMap::from([(full.clone(), entry(&full, "old"))]);
+        let report = merge(&mut data, vec![entry(&full, "new")], CollisionStrategy::Rename).unwrap();
+        assert_eq!(report.renamed[0].1, format!("{} (2)", "b".repeat(96)));
+    }
+}
@@ -0,0 +1,187 @@
+use super::{finish, from_unix_secs, Draft, ImportBatch, Importer};
+use crate::error::{Error, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::io::{Cursor, Read};
+
+/// 1Password `.1pux` export: a zip whose `export.data` holds every
+/// account's vaults as JSON. The 1Password vault name becomes the folder.
+pub struct OnePuxImporter;
+
+#[derive(Deserialize)]
+struct Export {
+    accounts: Vec<Account>,
+}
+
+#[derive(Deserialize)]
+struct Account {
+    #[serde(default)]
+    vaults: Vec<Vault>,
+}
+
+#[derive(Deserialize)]
+struct Vault {
+    attrs: VaultAttrs,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct VaultAttrs {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(default)]
+    category_uuid: String,
+    #[serde(default)]
+    state: String,
+    created_at: Option<i64>,
+    updated_at: Option<i64>,
+    overview: Overview,
+    details: Details,
+}
+
+#[derive(Deserialize)]
+struct Overview {
+    #[serde(default)]
+    title: String,
+    url: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Details {
+    #[serde(default)]
+    login_fields: Vec<LoginField>,
+    notes_plain: Option<String>,
+    /// Set on items of the Password category.
+    password: Option<String>,
+    #[serde(default)]
+    sections: Vec<Section>,
+}
+
+#[derive(Deserialize)]
+struct LoginField {
+    #[serde(default)]
+    value: String,
+    #[serde(default)]
+    designation: String,
+}
+
+#[derive(Deserialize)]
+struct Section {
+    #[serde(default)]
+    fields: Vec<SectionField>,
+}
+
+#[derive(Deserialize)]
+struct SectionField {
+    value: Value,
+}
+
+const LOGIN: &str = "001";
+const PASSWORD: &str = "005";
+
+impl Item {
+    fn login_field(&self, designation: &str) -> Option<String> {
+        self.details.login_fields.iter()
+            .find(|field| field.designation == designation)
+            .map(|field| field.value.clone())
+    }
+
+    fn totp(&self) -> Option<String> {
+        self.details.sections.iter()
+            .flat_map(|section| &section.fields)
+            .find_map(|field| field.value.get("totp")?.as_str().map(str::to_string))
+    }
+}
+
+impl Importer for OnePuxImporter {
+    fn parse(&self, data: &[u8]) -> Result<ImportBatch> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(data))
+            .map_err(|e| Error::InvalidInput(format!("Not a 1PUX archive: {}", e)))?;
+        let mut json = Vec::new();
+        archive.by_name("export.data")
+            .map_err(|e| Error::InvalidInput(format!("Not a 1PUX archive: {}", e)))?
+            .read_to_end(&mut json)?;
+        let export: Export = serde_json::from_slice(&json)
+            .map_err(|e| Error::InvalidInput(format!("Malformed 1PUX export: {}", e)))?;
+
+        let mut drafts = Vec::new();
+        let mut unsupported = Vec::new();
+        for vault in export.accounts.into_iter().flat_map(|account| account.vaults) {
+            for item in vault.items {
+                if item.state == "deleted" {
+                    continue;
+                }
+                let password = match item.category_uuid.as_str() {
+                    LOGIN => item.login_field("password"),
+                    PASSWORD => item.details.password.clone(),
+                    _ => {
+                        unsupported.push(item.overview.title);
+                        continue;
+                    }
+                };
+
+                drafts.push(Draft {
+                    folder: vec![vault.attrs.name.clone()],
+                    username: item.login_field("username"),
+                    password,
+                    otp: item.totp(),
+                    created_at: item.created_at.and_then(from_unix_secs),
+                    modified_at: item.updated_at.and_then(from_unix_secs),
+                    title: item.overview.title,
+                    url: item.overview.url,
+                    notes: item.details.notes_plain,
+                    tags: item.overview.tags,
+                });
+            }
+        }
+        finish(drafts, unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn logins_and_passwords_are_imported() {
+        let export = r#"{"accounts": [{"attrs": {}, "vaults": [{"attrs": {"name": "Private"}, "items": [
+            {"categoryUuid": "001", "state": "active", "createdAt": 1700000000, "updatedAt": 1700000100,
+             "overview": {"title": "Email", "url": "https://mail.example.com", "tags": ["personal"]},
+             "details": {"loginFields": [
+                 {"value": "me@example.com", "designation": "username"},
+                 {"value": "s3cret", "designation": "password"}],
+                 "notesPlain": "",
+                 "sections": [{"fields": [{"title": "otp", "value": {"totp": "JBSWY3DPEHPK3PXP"}}]}]}},
+            {"categoryUuid": "005", "state": "active",
+             "overview": {"title": "Router"}, "details": {"password": "admin"}},
+            {"categoryUuid": "002", "state": "active",
+             "overview": {"title": "Credit card"}, "details": {}}
+        ]}]}]}"#;
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("export.data", SimpleFileOptions::default()).unwrap();
+        zip.write_all(export.as_bytes()).unwrap();
+        let archive = zip.finish().unwrap().into_inner();
+
+        let batch = OnePuxImporter.parse(&archive).unwrap();
+        assert_eq!(batch.unsupported, ["Credit card"]);
+        let names: Vec<&str> = batch.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["Private/Email", "Private/Router"]);
+
+        let email = &batch.entries[0];
+        assert_eq!(email.metadata.username.as_deref(), Some("me@example.com"));
+        assert_eq!(email.metadata.notes, None);
+        assert_eq!(email.metadata.tags, ["personal"]);
+        assert!(email.otp.is_some());
+    }
+}
@@ -2,12 +2,27 @@ pub mod domain;
 pub mod error;
 pub mod repository;
 // pub mod services;
+pub mod credential;
 pub mod crypto;
+pub mod agent;
+pub mod generator;
+pub mod export;
+pub mod import;
+pub mod search;
+pub mod ssh;
+pub mod template;
+pub mod timestamp;
+pub mod vaults;
 
 pub use error::{Result, Error};
-pub use repository::Store;
-pub use domain::{PasswordEntry, EntryName, Password};
-pub use crypto::{CryptoManager, EncryptedVault};
+pub use repository::{Store, StoreRepository, SessionPolicy};
+pub use domain::{PasswordEntry, EntryMetadata, EntryName, EntryQuery, EntrySummary, NameTree, OtpSecret, Password, SshKeyInfo};
+pub use crypto::{CryptoManager, EncryptedVault, KdfParams};
+pub use agent::AgentClient;
+pub use generator::Recipe;
+pub use export::ExportFormat;
+pub use import::{CollisionStrategy, ImportReport, Importer};
+pub use vaults::{VaultLocation, VaultRegistry};
 
 
 pub fn add(left: u64, right: u64) -> u64 {
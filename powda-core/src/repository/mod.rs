@@ -1,2 +1,6 @@
+mod session;
+mod storage;
 mod store;
-pub use store::{Store, StoreRepository};
+pub use session::{SessionClock, SessionPolicy};
+pub use storage::{atomic_write, BackupInfo};
+pub use store::{Store, StoreRepository, DEFAULT_BACKUP_COUNT};
@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+/// Limits on how long an unlocked vault may stay unlocked.
+/// `None` disables the corresponding limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionPolicy {
+    pub idle_timeout: Option<Duration>,
+    pub max_lifetime: Option<Duration>,
+}
+
+impl SessionPolicy {
+    pub fn new(idle_timeout: Option<Duration>, max_lifetime: Option<Duration>) -> Self {
+        Self { idle_timeout, max_lifetime }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.idle_timeout.is_none() && self.max_lifetime.is_none()
+    }
+}
+
+/// Tracks when a session was unlocked and last used.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionClock {
+    unlocked_at: Instant,
+    last_activity: Instant,
+}
+
+impl SessionClock {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self { unlocked_at: now, last_activity: now }
+    }
+
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn is_expired(&self, policy: &SessionPolicy) -> bool {
+        let idle = policy.idle_timeout
+            .is_some_and(|limit| self.last_activity.elapsed() >= limit);
+        let lifetime = policy.max_lifetime
+            .is_some_and(|limit| self.unlocked_at.elapsed() >= limit);
+        idle || lifetime
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_policy_never_expires() {
+        let clock = SessionClock::start();
+        assert!(!clock.is_expired(&SessionPolicy::default()));
+    }
+
+    #[test]
+    fn zero_idle_timeout_expires_immediately() {
+        let clock = SessionClock::start();
+        let policy = SessionPolicy::new(Some(Duration::ZERO), None);
+        assert!(clock.is_expired(&policy));
+    }
+
+    #[test]
+    fn touch_does_not_extend_max_lifetime() {
+        let mut clock = SessionClock::start();
+        let policy = SessionPolicy::new(Some(Duration::from_secs(60)), Some(Duration::ZERO));
+        clock.touch();
+        assert!(clock.is_expired(&policy));
+    }
+}
@@ -0,0 +1,162 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Replace `path` with `contents` so that a crash leaves either the old
+/// file or the new one, never a truncated mix. The file ends up 0600.
+pub fn atomic_write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let dir = parent_dir(path);
+    let (tmp, mut file) = create_temp(path)?;
+
+    let result = (|| {
+        // The mode given at creation is filtered through the umask; force it.
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    })();
+
+    if result.is_err() {
+        fs::remove_file(&tmp).ok();
+        return result;
+    }
+
+    // Persist the rename itself.
+    File::open(dir)?.sync_all()
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Create a fresh 0600 temp file next to `path`. The name carries a random
+/// suffix and `create_new` refuses to reuse one, so concurrent writers -
+/// threads included - never share a temp file.
+fn create_temp(path: &Path) -> io::Result<(PathBuf, File)> {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    loop {
+        let tmp = parent_dir(path).join(format!(".{}.tmp.{:016x}", name, rand::random::<u64>()));
+        match OpenOptions::new().write(true).create_new(true).mode(0o600).open(&tmp) {
+            Ok(file) => return Ok((tmp, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+pub fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".bak.{}", index));
+    PathBuf::from(name)
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupInfo {
+    pub index: usize,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    pub size: u64,
+}
+
+/// Shift `.bak.1..keep` up by one and copy the current file into `.bak.1`.
+/// The oldest backup falls off the end.
+pub fn rotate_backups(path: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    let oldest = backup_path(path, keep);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for index in (1..keep).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, index + 1))?;
+        }
+    }
+
+    atomic_write(&backup_path(path, 1), &fs::read(path)?)
+}
+
+/// Backups of `path`, newest (index 1) first.
+pub fn list_backups(path: &Path, keep: usize) -> io::Result<Vec<BackupInfo>> {
+    let mut backups = Vec::new();
+    for index in 1..=keep {
+        let backup = backup_path(path, index);
+        let meta = match fs::metadata(&backup) {
+            Ok(meta) => meta,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        backups.push(BackupInfo {
+            index,
+            path: backup,
+            modified: meta.modified()?,
+            size: meta.len(),
+        });
+    }
+    Ok(backups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_keeps_newest_states() {
+        let dir = std::env::temp_dir().join(format!("powda-storage-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("vault");
+
+        for generation in 0..4 {
+            rotate_backups(&path, 2).unwrap();
+            atomic_write(&path, format!("state {}", generation).as_bytes()).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "state 3");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "state 2");
+        assert_eq!(fs::read_to_string(backup_path(&path, 2)).unwrap(), "state 1");
+        assert!(!backup_path(&path, 3).exists());
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_writes_in_one_process_do_not_share_a_temp_file() {
+        let dir = std::env::temp_dir().join(format!("powda-storage-threads-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("vault");
+
+        let writers: Vec<_> = (0..8).map(|writer| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let contents = format!("writer {}", writer).repeat(4096);
+                for _ in 0..20 {
+                    atomic_write(&path, contents.as_bytes()).unwrap();
+                }
+            })
+        }).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        // Whichever writer won, the file holds exactly one writer's contents.
+        let contents = fs::read_to_string(&path).unwrap();
+        let first = &contents[..8];
+        assert_eq!(contents, first.repeat(4096));
+        let leftovers: Vec<_> = fs::read_dir(&dir).unwrap()
+            .map(|e| e.unwrap().file_name())
+            .filter(|name| name != "vault")
+            .collect();
+        assert!(leftovers.is_empty(), "temp files left behind: {:?}", leftovers);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,42 +1,69 @@
-use crate::domain::{PasswordEntry, EntryName};
+use crate::domain::{PasswordEntry, EntryName, EntryQuery, EntrySummary, HistoryRetention, RetentionChange};
+use crate::import::{self, CollisionStrategy, ImportReport};
+use crate::vaults::{self, VaultRegistry};
 use crate::error::{Error, Result};
-use crate::crypto::{CryptoManager, EncryptedVault, encoding};
+use crate::crypto::{CryptoManager, EncryptedVault, KdfParams, VaultHeader};
 use async_trait::async_trait;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::fs;
+use std::path::{Path, PathBuf};
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use super::session::{SessionClock, SessionPolicy};
+use super::storage::{self, BackupInfo};
 
 #[async_trait]
 pub trait StoreRepository: Send + Sync {
-    async fn init(&self, master_password: &str) -> Result<()>;
+    async fn init(&self, master_password: &str, kdf: KdfParams) -> Result<()>;
     async fn unlock(&self, master_password: &str) -> Result<()>;
     async fn lock(&self) -> Result<()>;
     async fn is_locked(&self) -> bool;
     async fn exists(&self) -> bool;
     async fn add(&self, entry: PasswordEntry) -> Result<()>;
     async fn get(&self, name: &EntryName) -> Result<PasswordEntry>;
+    /// Every entry name, sorted.
     async fn list(&self) -> Result<Vec<EntryName>>;
+    /// Every entry, sorted by name, read from a single snapshot.
+    async fn entries(&self) -> Result<Vec<PasswordEntry>>;
+    /// Summaries of the entries matching `query`, sorted by name.
+    async fn query(&self, query: &EntryQuery) -> Result<Vec<EntrySummary>>;
     async fn update(&self, entry: PasswordEntry) -> Result<()>;
     async fn remove(&self, name: &EntryName) -> Result<()>;
+    /// Rename the entry `from` and everything in the folder `from` to sit
+    /// under `to` instead, returning the old and new name of each.
+    async fn rename(&self, from: &EntryName, to: &EntryName) -> Result<Vec<(EntryName, EntryName)>>;
     async fn change_master_password(&self, current: &str, new: &str) -> Result<()>;
+    async fn upgrade_kdf(&self, master_password: &str, kdf: KdfParams) -> Result<()>;
+    async fn history_retention(&self) -> Result<HistoryRetention>;
+    /// Apply `change` to the retention policy and trim every entry's
+    /// history to the result, reading and writing the policy under one
+    /// vault lock. Returns the new policy.
+    async fn update_history_retention(&self, change: RetentionChange) -> Result<HistoryRetention>;
+    /// Add `entries` in a single write, resolving name clashes with `strategy`.
+    async fn import(&self, entries: Vec<PasswordEntry>, strategy: CollisionStrategy) -> Result<ImportReport>;
 }
 
+/// Encrypted copies of earlier vault states kept next to the vault.
+pub const DEFAULT_BACKUP_COUNT: usize = 5;
+
 pub struct Store {
     path: PathBuf,
     crypto: Arc<Mutex<CryptoManager>>,
     cache: Arc<Mutex<Option<HashMap<String, PasswordEntry>>>>,
+    policy: SessionPolicy,
+    session: Arc<Mutex<Option<SessionClock>>>,
+    /// Header authenticated at unlock; saves reuse it instead of
+    /// trusting whatever is on disk at that moment.
+    header: Arc<Mutex<Option<VaultHeader>>>,
+    backup_count: usize,
 }
 
 impl Store {
-    pub fn new() -> Self {
-        let home = std::env::var("HOME").expect("HOME not set");
-        let path = PathBuf::from(home).join(".powda_vault.encrypted");
-        Store {
-            path,
-            crypto: Arc::new(Mutex::new(CryptoManager::new())),
-            cache: Arc::new(Mutex::new(None)),
-}
+    /// Store for the vault `powda` uses when no `--vault` is given.
+    pub fn new() -> Result<Self> {
+        let location = VaultRegistry::load()?.resolve_configured()?;
+        Ok(Self::with_path(location.path))
     }
 
     pub fn with_path(path: PathBuf) -> Self {
@@ -44,7 +71,179 @@ impl Store {
             path,
             crypto: Arc::new(Mutex::new(CryptoManager::new())),
             cache: Arc::new(Mutex::new(None)),
+            policy: SessionPolicy::default(),
+            session: Arc::new(Mutex::new(None)),
+            header: Arc::new(Mutex::new(None)),
+            backup_count: DEFAULT_BACKUP_COUNT,
+        }
+    }
+
+    pub fn with_session_policy(mut self, policy: SessionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_backup_count(mut self, count: usize) -> Self {
+        self.backup_count = count;
+        self
+    }
+
+    pub fn backups(&self) -> Result<Vec<BackupInfo>> {
+        Ok(storage::list_backups(&self.path, self.backup_count)?)
+    }
+
+    /// Roll the vault back to backup `index` (1 is the newest). The state
+    /// being replaced becomes the newest backup, so a restore can be undone.
+    pub fn restore_backup(&self, index: usize) -> Result<()> {
+        let backup = storage::backup_path(&self.path, index);
+        if index == 0 || index > self.backup_count || !backup.exists() {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no backup {}", index),
+            )));
+        }
+
+        let contents = fs::read(&backup)?;
+        let _: EncryptedVault = serde_json::from_slice(&contents)?;
+
+        let _lock = self.lock_vault_file(true)?;
+        storage::rotate_backups(&self.path, self.backup_count)?;
+        storage::atomic_write(&self.path, &contents)?;
+
+        // Whatever was unlocked belongs to the state we just replaced.
+        self.clear_session();
+        Ok(())
+    }
+
+    /// Replace the vault with a new, empty one. The old vault is kept as
+    /// the newest backup, so `restore_backup(1)` brings it back.
+    pub fn reinit(&self, master_password: &str, kdf: KdfParams) -> Result<()> {
+        self.create(master_password, kdf, true)
+    }
+
+    fn create(&self, master_password: &str, kdf: KdfParams, replace: bool) -> Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            vaults::create_private_dir(dir)?;
+        }
+        let _lock = self.lock_vault_file(true)?;
+        if self.path.exists() && !replace {
+            return Err(Error::AlreadyExists("Store".to_string()));
+        }
+
+        let data: HashMap<String, PasswordEntry> = HashMap::new();
+        let json_data = serde_json::to_vec(&data)?;
+
+        let mut crypto = self.crypto.lock().unwrap();
+        let vault = crypto.create_vault(master_password, &json_data, kdf)?;
+        self.write_vault_file(&vault)?;
+        *self.header.lock().unwrap() = Some(vault.header);
+
+        let mut cache = self.cache.lock().unwrap();
+        *cache = Some(data);
+        drop(cache);
+
+        self.start_session();
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn session_policy(&self) -> SessionPolicy {
+        self.policy
+    }
+
+    /// Lock the vault if the session outlived its policy. Long-lived
+    /// processes call this periodically so the key does not linger in
+    /// memory until the next access. Returns true if it locked.
+    pub fn lock_if_expired(&self) -> bool {
+        let expired = {
+            let session = self.session.lock().unwrap();
+            session.is_some_and(|clock| clock.is_expired(&self.policy))
+        };
+
+        if expired {
+            self.clear_session();
         }
+        expired
+    }
+
+    fn start_session(&self) {
+        let mut session = self.session.lock().unwrap();
+        *session = Some(SessionClock::start());
+    }
+
+    fn clear_session(&self) {
+        // Dropping the cached entries and the MasterKey zeroizes them.
+        *self.cache.lock().unwrap() = None;
+        self.crypto.lock().unwrap().lock();
+        *self.session.lock().unwrap() = None;
+        *self.header.lock().unwrap() = None;
+    }
+
+    /// Advisory lock shared by every powda process using this vault,
+    /// released when the returned file is dropped. It lives in a sidecar
+    /// file because the vault itself is replaced by rename on each write.
+    fn lock_vault_file(&self, exclusive: bool) -> Result<File> {
+        let mut lock_path = self.path.as_os_str().to_owned();
+        lock_path.push(".lock");
+
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(lock_path)?;
+
+        if exclusive {
+            file.lock()?;
+        } else {
+            file.lock_shared()?;
+        }
+        Ok(file)
+    }
+
+    fn read_vault_file(&self) -> Result<EncryptedVault> {
+        if !self.path.exists() {
+            return Err(Error::NotInitialized);
+        }
+        let vault_json = fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&vault_json)?)
+    }
+
+    /// KDF cost the vault on disk was created with.
+    pub fn kdf_params(&self) -> Result<KdfParams> {
+        Ok(self.read_vault_file()?.header.kdf)
+    }
+
+    fn write_vault_file(&self, vault: &EncryptedVault) -> Result<()> {
+        let vault_json = serde_json::to_string_pretty(vault)?;
+        storage::rotate_backups(&self.path, self.backup_count)?;
+        storage::atomic_write(&self.path, vault_json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Wrap the data key under a key derived from `new` with `kdf`.
+    /// The data key itself does not change.
+    fn rewrap(&self, current: &str, new: &str, kdf: KdfParams) -> Result<()> {
+        let _lock = self.lock_vault_file(true)?;
+
+        // Verify current password and load data
+        let data = self.load_vault(current)?;
+        let json_data = serde_json::to_vec(&data)?;
+
+        let mut vault = self.read_vault_file()?;
+        vault.header.generation += 1;
+        let rewrapped = self.crypto.lock().unwrap().rewrap_vault(&vault, new, kdf, &json_data)?;
+        self.write_vault_file(&rewrapped)?;
+        *self.header.lock().unwrap() = Some(rewrapped.header);
+
+        // Update cache
+        *self.cache.lock().unwrap() = Some(data);
+
+        self.start_session();
+        Ok(())
     }
 
     fn load_vault(&self, master_password: &str) -> Result<HashMap<String, PasswordEntry>> {
@@ -53,12 +252,21 @@ impl Store {
             return Err(Error::NotInitialized);
         }
 
-        let vault_json = fs::read_to_string(&self.path)?;
-        let vault: EncryptedVault = serde_json::from_str(&vault_json)?;
+        let vault = self.read_vault_file()?;
 
         let mut crypto = self.crypto.lock().unwrap();
         let decrypted = crypto.open_vault(master_password, &vault)?;
 
+        let header = if vault.needs_upgrade() {
+            // First unlock of an old vault: move it to the current layout.
+            let upgraded = crypto.create_vault(master_password, &decrypted, vault.header.kdf)?;
+            self.write_vault_file(&upgraded)?;
+            upgraded.header
+        } else {
+            vault.header
+        };
+        *self.header.lock().unwrap() = Some(header);
+
         let data: HashMap<String, PasswordEntry> = serde_json::from_slice(&decrypted)?;
 
         Ok(data)
@@ -72,30 +280,89 @@ impl Store {
             return Err(Error::Encryption("Vault is locked".to_string()));
         }
 
-        let (nonce, ciphertext) = crypto.encrypt(&json_data)?;
-
-        let existing_vault: EncryptedVault = if self.path.exists() {
-            let vault_json = fs::read_to_string(&self.path)?;
-            serde_json::from_str(&vault_json)?
-        } else {
+        if !self.path.exists() {
             return Err(Error::Encryption("Cannot save - vault not initialized".to_string()));
-        };
+        }
+        let mut header = self.header.lock().unwrap().clone()
+            .ok_or_else(|| Error::Encryption("Vault is locked".to_string()))?;
+        header.generation += 1;
 
-        let vault = EncryptedVault {
-            salt: existing_vault.salt,
-            argon2_params: existing_vault.argon2_params,
-            nonce: encoding::encode(&nonce),
-            ciphertext: encoding::encode(&ciphertext),
-            version: 1
+        let vault = crypto.seal_vault(header, &json_data)?;
+        self.write_vault_file(&vault)?;
+        *self.header.lock().unwrap() = Some(vault.header);
+        Ok(())
+    }
+
+    /// Pick up writes other processes made since we unlocked. As long as
+    /// the data key is unchanged the newer vault simply replaces the cache;
+    /// if it was re-keyed we cannot read it and the session ends.
+    /// Callers hold the vault file lock.
+    fn sync_with_disk(&self) -> Result<()> {
+        let known = match self.header.lock().unwrap().clone() {
+            Some(header) => header,
+            None => return Ok(()),
         };
 
-        let vault_json = serde_json::to_string_pretty(&vault)?;
-        fs::write(&self.path, vault_json)?;
+        let disk = self.read_vault_file()?;
+        if disk.header == known {
+            return Ok(());
+        }
+
+        let decrypted = self.crypto.lock().unwrap().reopen_vault(&disk);
+        let decrypted = match decrypted {
+            Ok(decrypted) => decrypted,
+            Err(_) => {
+                self.clear_session();
+                return Err(Error::Conflict(
+                    "it was re-keyed by another process, unlock again".to_string(),
+                ));
+            }
+        };
 
+        let data: HashMap<String, PasswordEntry> = serde_json::from_slice(&decrypted)?;
+        *self.cache.lock().unwrap() = Some(data);
+        *self.header.lock().unwrap() = Some(disk.header);
         Ok(())
     }
 
+    fn read_data(&self) -> Result<HashMap<String, PasswordEntry>> {
+        let _lock = self.lock_vault_file(false)?;
+        self.get_data()
+    }
+
+    /// Read-modify-write under the exclusive vault lock, applying `change`
+    /// to the newest state on disk so concurrent writers do not lose each
+    /// other's changes.
+    fn modify<F>(&self, change: F) -> Result<()>
+    where
+        F: FnOnce(&mut HashMap<String, PasswordEntry>) -> Result<()>,
+    {
+        let _lock = self.lock_vault_file(true)?;
+        let mut data = self.get_data()?;
+        change(&mut data)?;
+        self.trim_history(&mut data);
+        self.save_data(&data)
+    }
+
+    /// Enforce the authenticated header's retention policy on every entry.
+    fn trim_history(&self, data: &mut HashMap<String, PasswordEntry>) {
+        let retention = self.header.lock().unwrap()
+            .as_ref()
+            .map(|header| header.history)
+            .unwrap_or_default();
+        let now = SystemTime::now();
+        for entry in data.values_mut() {
+            retention.apply(&mut entry.history, now);
+        }
+    }
+
     fn get_data(&self) -> Result<HashMap<String, PasswordEntry>> {
+        self.lock_if_expired();
+        if let Some(clock) = self.session.lock().unwrap().as_mut() {
+            clock.touch();
+        }
+        self.sync_with_disk()?;
+
         let cache = self.cache.lock().unwrap();
 
         match &*cache {
@@ -110,56 +377,37 @@ impl Store {
             *cache = Some(data.clone());
         }
 
-        self.save_vault(&data)?;
+        self.save_vault(data)?;
         Ok(())
     }
 }
 
-
 #[async_trait]
 impl StoreRepository for Store {
 
-    async fn init(&self, master_password: &str) -> Result<()> {
-        if self.path.exists() {
-            return Err(Error::AlreadyExists("Store".to_string()));
-        }
-
-        let data: HashMap<String, PasswordEntry> = HashMap::new();
-        let json_data = serde_json::to_vec(&data)?;
-
-        let mut crypto = self.crypto.lock().unwrap();
-        let vault = crypto.create_vault(master_password, &json_data)?;
-
-        let vault_json = serde_json::to_string_pretty(&vault)?;
-        fs::write(&self.path, vault_json)?;
-
-        let mut cache = self.cache.lock().unwrap();
-        *cache = Some(data);
-
-
-        Ok(())
+    async fn init(&self, master_password: &str, kdf: KdfParams) -> Result<()> {
+        self.create(master_password, kdf, false)
     }
 
     async fn unlock(&self, master_password: &str) -> Result<()> {
+        let _lock = self.lock_vault_file(true)?;
         let data = self.load_vault(master_password)?;
 
         let mut cache = self.cache.lock().unwrap();
         *cache = Some(data);
+        drop(cache);
 
+        self.start_session();
         Ok(())
     }
 
     async fn lock(&self) -> Result<()> {
-        let mut cache = self.cache.lock().unwrap();
-        *cache = None;
-
-        let mut crypto = self.crypto.lock().unwrap();
-        crypto.lock();
-
+        self.clear_session();
         Ok(())
     }
 
     async fn is_locked(&self) -> bool {
+        self.lock_if_expired();
         let cache = self.cache.lock().unwrap();
         cache.is_none()
     }
@@ -169,74 +417,257 @@ impl StoreRepository for Store {
     }
 
     async fn add(&self, entry: PasswordEntry) -> Result<()> {
-        let mut data = self.get_data()?;
-
-        let key = entry.name.as_str().to_string();
-        if data.contains_key(&key) {
-             return Err(Error::AlreadyExists(key));
-        }
-
-        data.insert(key, entry);
-        self.save_data(&data)?;
-        Ok(())
+        self.modify(|data| {
+            let key = entry.name.as_str().to_string();
+            if data.contains_key(&key) {
+                return Err(Error::AlreadyExists(key));
+            }
+
+            data.insert(key, entry);
+            Ok(())
+        })
     }
 
     async fn get(&self, name: &EntryName) -> Result<PasswordEntry> {
-        let data = self.get_data()?;
+        let data = self.read_data()?;
         data.get(name.as_str())
             .cloned()
             .ok_or_else(|| Error::NotFound(name.as_str().to_string()))
     }
 
     async fn list(&self) -> Result<Vec<EntryName>> {
-        let data = self.get_data()?;
-        data.values()
-            .map(|entry| Ok(entry.name.clone()))
-            .collect()
+        let mut names: Vec<EntryName> = self.read_data()?.into_values().map(|entry| entry.name).collect();
+        names.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        Ok(names)
+    }
+
+    async fn entries(&self) -> Result<Vec<PasswordEntry>> {
+        let mut entries: Vec<PasswordEntry> = self.read_data()?.into_values().collect();
+        entries.sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+        Ok(entries)
+    }
+
+    async fn query(&self, query: &EntryQuery) -> Result<Vec<EntrySummary>> {
+        let data = self.read_data()?;
+        let mut found: Vec<EntrySummary> = data.values()
+            .filter(|entry| query.matches(entry))
+            .map(EntrySummary::from)
+            .collect();
+        found.sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+        Ok(found)
     }
 
     async fn update(&self, entry: PasswordEntry) -> Result<()> {
-        let mut data = self.get_data()?;
-        data.insert(entry.name.as_str().to_string(), entry);
-        self.save_data(&data)?;
-        Ok(())
+        self.modify(|data| {
+            data.insert(entry.name.as_str().to_string(), entry);
+            Ok(())
+        })
     }
 
     async fn remove(&self, name: &EntryName) -> Result<()> {
-        let mut data = self.get_data()?;
+        self.modify(|data| {
+            if data.remove(name.as_str()).is_none() {
+                return Err(Error::NotFound(name.as_str().to_string()));
+            }
+            Ok(())
+        })
+    }
 
-        if data.remove(name.as_str()).is_none() {
-            return Err(Error::NotFound(name.as_str().to_string()));
+    async fn rename(&self, from: &EntryName, to: &EntryName) -> Result<Vec<(EntryName, EntryName)>> {
+        if to.is_under(from.as_str()) {
+            return Err(Error::InvalidInput(format!("Cannot move '{}' into itself", from.as_str())));
         }
 
-        self.save_data(&data)?;
-        Ok(())
+        let mut moved = Vec::new();
+        self.modify(|data| {
+            let mut old_names: Vec<&String> = data.keys()
+                .filter(|key| EntryName::new(key.to_string()).is_ok_and(|name| name.is_under(from.as_str())))
+                .collect();
+            if old_names.is_empty() {
+                return Err(Error::NotFound(from.as_str().to_string()));
+            }
+            old_names.sort();
+
+            // Check every new name before touching anything.
+            let mut renames = Vec::with_capacity(old_names.len());
+            for old in old_names {
+                let new = format!("{}{}", to.as_str(), &old[from.as_str().len()..]);
+                let new = EntryName::new(new).map_err(Error::InvalidInput)?;
+                if data.contains_key(new.as_str()) {
+                    return Err(Error::AlreadyExists(new.as_str().to_string()));
+                }
+                renames.push((old.clone(), new));
+            }
+
+            for (old, new) in renames {
+                let mut entry = data.remove(&old).expect("name was just listed");
+                let old = std::mem::replace(&mut entry.name, new.clone());
+                data.insert(new.as_str().to_string(), entry);
+                moved.push((old, new));
+            }
+            Ok(())
+        })?;
+        Ok(moved)
     }
 
     async fn change_master_password(&self, current: &str, new: &str) -> Result<()> {
-        // Verify current password and load data
-        let data = self.load_vault(current)?;
-        
-        // Create new vault with new password
-        let json_data = serde_json::to_vec(&data)?;
-        let mut crypto = CryptoManager::new();
-        let vault = crypto.create_vault(new, &json_data)?;
-        
-        // Save new vault
-        let vault_json = serde_json::to_string_pretty(&vault)?;
-        fs::write(&self.path, vault_json)?;
-        
-        // Update current crypto manager
-        let mut current_crypto = self.crypto.lock().unwrap();
-        *current_crypto = crypto;
-        
-        // Update cache
-        let mut cache = self.cache.lock().unwrap();
-        *cache = Some(data);
-        
-        Ok(())
+        let kdf = self.kdf_params()?;
+        self.rewrap(current, new, kdf)
+    }
+
+    async fn upgrade_kdf(&self, master_password: &str, kdf: KdfParams) -> Result<()> {
+        self.rewrap(master_password, master_password, kdf)
+    }
+
+    async fn history_retention(&self) -> Result<HistoryRetention> {
+        let _lock = self.lock_vault_file(false)?;
+        Ok(self.read_vault_file()?.header.history)
+    }
+
+    async fn update_history_retention(&self, change: RetentionChange) -> Result<HistoryRetention> {
+        let mut retention = HistoryRetention::default();
+        self.modify(|_| {
+            if let Some(header) = self.header.lock().unwrap().as_mut() {
+                header.history = change.apply_to(header.history);
+                retention = header.history;
+            }
+            Ok(())
+        })?;
+        Ok(retention)
+    }
+
+    async fn import(&self, entries: Vec<PasswordEntry>, strategy: CollisionStrategy) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        self.modify(|data| {
+            report = import::merge(data, entries, strategy)?;
+            Ok(())
+        })?;
+        Ok(report)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Password;
+
+    fn temp_vault(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("powda-store-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("vault")
+    }
+
+    fn entry(name: &str) -> PasswordEntry {
+        PasswordEntry::new(EntryName::new(name.to_string()).unwrap(), Password::new("pw".to_string()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn concurrent_writers_keep_both_changes() {
+        let path = temp_vault("merge");
+        let first = Store::with_path(path.clone());
+        first.init("master password", KdfParams::new(8, 1, 1).unwrap()).await.unwrap();
+        let second = Store::with_path(path.clone());
+        second.unlock("master password").await.unwrap();
+
+        first.add(entry("from-first")).await.unwrap();
+        second.add(entry("from-second")).await.unwrap();
+
+        let mut names: Vec<String> = first.list().await.unwrap()
+            .into_iter().map(|n| n.as_str().to_string()).collect();
+        names.sort();
+        assert_eq!(names, ["from-first", "from-second"]);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn retention_policy_trims_history_and_is_persisted() {
+        let path = temp_vault("history");
+        let store = Store::with_path(path.clone());
+        store.init("master password", KdfParams::new(8, 1, 1).unwrap()).await.unwrap();
+        store.add(entry("db")).await.unwrap();
+
+        let name = EntryName::new("db".to_string()).unwrap();
+        for i in 0..4 {
+            let mut db = store.get(&name).await.unwrap();
+            db.update_password(Password::new(format!("pw{}", i)).unwrap());
+            store.update(db).await.unwrap();
+        }
+        assert_eq!(store.get(&name).await.unwrap().history.len(), 4);
+
+        let retention = store.update_history_retention(RetentionChange { max_versions: Some(2), max_age_days: None })
+            .await.unwrap();
+        assert_eq!(retention, HistoryRetention { max_versions: 2, max_age_days: None });
+        let history: Vec<String> = store.get(&name).await.unwrap().history.iter()
+            .map(|v| v.password.as_str().to_string()).collect();
+        assert_eq!(history, ["pw2", "pw1"]);
+
+        let reopened = Store::with_path(path.clone());
+        reopened.unlock("master password").await.unwrap();
+        assert_eq!(reopened.history_retention().await.unwrap(), retention);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rename_moves_entries_and_whole_folders() {
+        let path = temp_vault("rename");
+        let store = Store::with_path(path.clone());
+        store.init("master password", KdfParams::new(8, 1, 1).unwrap()).await.unwrap();
+        for name in ["work", "work/aws/prod", "work/vpn", "workshop", "home/vpn"] {
+            store.add(entry(name)).await.unwrap();
+        }
+        let name = |n: &str| EntryName::new(n.to_string()).unwrap();
+
+        let moved = store.rename(&name("work"), &name("old/work")).await.unwrap();
+        assert_eq!(moved.len(), 3);
+        let names: Vec<String> = store.list().await.unwrap().iter().map(|n| n.as_str().to_string()).collect();
+        assert_eq!(names, ["home/vpn", "old/work", "old/work/aws/prod", "old/work/vpn", "workshop"]);
+        assert_eq!(store.get(&name("old/work/vpn")).await.unwrap().name, name("old/work/vpn"));
+
+        // Nothing moves if any target is taken.
+        assert!(matches!(store.rename(&name("home"), &name("old/work")).await, Err(Error::AlreadyExists(_))));
+        assert!(store.rename(&name("old"), &name("old/nested")).await.is_err());
+        assert!(matches!(store.rename(&name("missing"), &name("x")).await, Err(Error::NotFound(_))));
 
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rekeyed_vault_is_a_conflict() {
+        let path = temp_vault("conflict");
+        let first = Store::with_path(path.clone());
+        first.init("master password", KdfParams::new(8, 1, 1).unwrap()).await.unwrap();
+        let second = Store::with_path(path.clone());
+        second.unlock("master password").await.unwrap();
+
+        // Re-initialising gives the vault a brand-new data key.
+        fs::remove_file(&path).unwrap();
+        Store::with_path(path.clone()).init("other password", KdfParams::new(8, 1, 1).unwrap()).await.unwrap();
+
+        assert!(matches!(second.add(entry("lost")).await, Err(Error::Conflict(_))));
+        assert!(second.is_locked().await);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
 
+    #[tokio::test]
+    async fn reinit_keeps_the_old_vault_as_the_newest_backup() {
+        let path = temp_vault("reinit");
+        let store = Store::with_path(path.clone());
+        store.init("master password", KdfParams::new(8, 1, 1).unwrap()).await.unwrap();
+        store.add(entry("db")).await.unwrap();
+        assert!(matches!(store.init("other", KdfParams::new(8, 1, 1).unwrap()).await, Err(Error::AlreadyExists(_))));
+
+        let fresh = Store::with_path(path.clone());
+        fresh.reinit("new password", KdfParams::new(8, 1, 1).unwrap()).unwrap();
+        assert!(fresh.list().await.unwrap().is_empty());
+
+        fresh.restore_backup(1).unwrap();
+        let restored = Store::with_path(path.clone());
+        restored.unlock("master password").await.unwrap();
+        assert_eq!(restored.list().await.unwrap().len(), 1);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
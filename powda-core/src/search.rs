@@ -0,0 +1,206 @@
+//! Ranked fuzzy matching of entries, shared by `powda get` and the TUI.
+//!
+//! A pattern matches a field when its characters appear in order,
+//! case-insensitively, not necessarily next to each other. Matches score
+//! higher when they start words (`gh` in `git-hub`), run together, or sit
+//! in the name rather than the username, URL host or tags.
+
+use crate::domain::{url_host, EntryName, EntrySummary};
+
+const MATCH: i64 = 16;
+const BOUNDARY: i64 = 10;
+const CAMEL_CASE: i64 = 6;
+const CONSECUTIVE: i64 = 10;
+const GAP_START: i64 = 3;
+const GAP_EXTENSION: i64 = 1;
+const EXACT: i64 = 100;
+/// Taken off matches outside the name, so a name match wins a tie.
+const OTHER_FIELD: i64 = 24;
+
+/// Where a pattern matched in one string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Indices of the matched characters (not bytes), for highlighting.
+    pub positions: Vec<usize>,
+}
+
+/// The field of an entry a search hit came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchField {
+    Name,
+    Username,
+    Host,
+    Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    pub name: EntryName,
+    pub score: i64,
+    pub field: MatchField,
+    /// Matched characters of the field in `field`.
+    pub positions: Vec<usize>,
+}
+
+/// Best-scoring way to match `pattern` in `text`, or `None` if its
+/// characters do not all appear in order. Whitespace in the pattern is
+/// ignored, so `aws prod` finds `aws/prod-root`.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(lower)
+        .collect();
+    let chars: Vec<char> = text.chars().collect();
+    let (m, n) = (pattern.len(), chars.len());
+    if m == 0 {
+        return Some(FuzzyMatch { score: 0, positions: Vec::new() });
+    }
+    if m > n {
+        return None;
+    }
+
+    // score[i][j]: best score with pattern[..=i] matched and pattern[i]
+    // on chars[j]; from[i][j]: where pattern[i - 1] went in that match.
+    let mut score: Vec<Vec<Option<i64>>> = vec![vec![None; n]; m];
+    let mut from = vec![vec![0usize; n]; m];
+
+    for i in 0..m {
+        // Best earlier match of pattern[i - 1] at least one character back,
+        // already charged for the gap up to j.
+        let mut gapped: Option<(i64, usize)> = None;
+        for j in 0..n {
+            if i > 0 && j >= 2 {
+                gapped = gapped.map(|(s, k)| (s - GAP_EXTENSION, k));
+                if let Some(s) = score[i - 1][j - 2]
+                    && gapped.is_none_or(|(best, _)| s - GAP_START > best) {
+                    gapped = Some((s - GAP_START, j - 2));
+                }
+            }
+            if lower(chars[j]) != pattern[i] {
+                continue;
+            }
+
+            let bonus = bonus(&chars, j);
+            let here = MATCH + bonus;
+            if i == 0 {
+                // Starting mid-word late in the text costs a little, capped
+                // so long URLs are not ruled out.
+                let late = if bonus == 0 { (j as i64).min(8) * GAP_EXTENSION } else { 0 };
+                score[0][j] = Some(here - late);
+                continue;
+            }
+            let adjacent = (j >= 1).then(|| score[i - 1][j - 1]).flatten().map(|s| (s + CONSECUTIVE, j - 1));
+            let best = match (adjacent, gapped) {
+                (Some(a), Some(g)) => Some(if a.0 >= g.0 { a } else { g }),
+                (a, g) => a.or(g),
+            };
+            if let Some((s, k)) = best {
+                score[i][j] = Some(s + here);
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let (mut j, mut total) = (0..n)
+        .filter_map(|j| score[m - 1][j].map(|s| (j, s)))
+        .max_by_key(|&(j, s)| (s, std::cmp::Reverse(j)))?;
+    let mut positions = vec![0; m];
+    for i in (0..m).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+
+    if m == n {
+        total += EXACT;
+    }
+    Some(FuzzyMatch { score: total, positions })
+}
+
+/// Entries matching `pattern` in their name, username, URL host or tags,
+/// best first. Each entry appears once, under its best-matching field.
+pub fn search<'a>(pattern: &str, entries: impl IntoIterator<Item = &'a EntrySummary>) -> Vec<SearchHit> {
+    let mut hits: Vec<SearchHit> = entries.into_iter()
+        .filter_map(|entry| best_field(pattern, entry))
+        .collect();
+    hits.sort_by(|a, b| b.score.cmp(&a.score)
+        .then_with(|| a.name.as_str().len().cmp(&b.name.as_str().len()))
+        .then_with(|| a.name.as_str().cmp(b.name.as_str())));
+    hits
+}
+
+fn best_field(pattern: &str, entry: &EntrySummary) -> Option<SearchHit> {
+    let name = fuzzy_match(pattern, entry.name.as_str()).map(|hit| {
+        // Typing the whole last part of a name counts as exact.
+        let exact = entry.name.leaf().eq_ignore_ascii_case(pattern.trim());
+        (MatchField::Name, hit.score + if exact { EXACT } else { 0 }, hit.positions)
+    });
+    let others = entry.username.as_deref().map(|u| (MatchField::Username, u)).into_iter()
+        .chain(entry.url.as_deref().and_then(url_host).map(|h| (MatchField::Host, h)))
+        .chain(entry.tags.iter().map(|t| (MatchField::Tag, t.as_str())))
+        .filter_map(|(field, text)| {
+            fuzzy_match(pattern, text).map(|hit| (field, hit.score - OTHER_FIELD, hit.positions))
+        });
+
+    let (field, score, positions) = name.into_iter().chain(others).max_by_key(|(_, score, _)| *score)?;
+    Some(SearchHit { name: entry.name.clone(), score, field, positions })
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Extra points for a match that starts a word.
+fn bonus(chars: &[char], j: usize) -> i64 {
+    let Some(&previous) = j.checked_sub(1).and_then(|p| chars.get(p)) else {
+        return BOUNDARY;
+    };
+    let current = chars[j];
+    if matches!(previous, '/' | '-' | '_' | '.' | ' ' | '@' | ':' | '(') {
+        BOUNDARY
+    } else if previous.is_lowercase() && current.is_uppercase() {
+        CAMEL_CASE
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{EntryMetadata, Password, PasswordEntry};
+
+    fn summary(name: &str, username: &str, url: &str) -> EntrySummary {
+        let entry = PasswordEntry::new(EntryName::new(name.to_string()).unwrap(), Password::new("pw".to_string()).unwrap())
+            .with_metadata(EntryMetadata {
+                url: Some(url.to_string()),
+                username: Some(username.to_string()),
+                ..Default::default()
+            });
+        EntrySummary::from(&entry)
+    }
+
+    #[test]
+    fn matches_are_subsequences_at_the_best_positions() {
+        assert_eq!(fuzzy_match("gh", "GitHub").unwrap().positions, [0, 3]);
+        assert_eq!(fuzzy_match("prod", "work/aws/prod-root").unwrap().positions, [9, 10, 11, 12]);
+        assert!(fuzzy_match("hg", "github").is_none());
+        assert!(fuzzy_match("gith", "github").unwrap().score > fuzzy_match("gith", "legit-hub").unwrap().score);
+    }
+
+    #[test]
+    fn search_ranks_names_over_other_fields() {
+        let entries = [
+            summary("mail", "gith@example.com", "https://mail.example.com"),
+            summary("dev/github", "alice", "https://github.com"),
+            summary("git-host", "bob", "https://git.example.com"),
+        ];
+        let hits = search("gith", &entries);
+        let names: Vec<&str> = hits.iter().map(|hit| hit.name.as_str()).collect();
+        assert_eq!(names, ["dev/github", "git-host", "mail"]);
+
+        let hits = search("example", &entries);
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|hit| hit.field != MatchField::Name));
+    }
+}
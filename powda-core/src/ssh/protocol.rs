@@ -0,0 +1,131 @@
+//! The subset of the OpenSSH agent protocol (draft-miller-ssh-agent) a
+//! vault-backed agent needs: listing keys and signing. Keys are added
+//! with `powda ssh add`, so requests to add, remove or lock keys are
+//! answered with a failure.
+//!
+//! Every message is a big-endian `u32` length followed by that many
+//! bytes, the first of which is the message type.
+
+use crate::error::{Error, Result};
+
+const FAILURE: u8 = 5;
+const REQUEST_IDENTITIES: u8 = 11;
+const IDENTITIES_ANSWER: u8 = 12;
+const SIGN_REQUEST: u8 = 13;
+const SIGN_RESPONSE: u8 = 14;
+
+/// Sign flags asking for `rsa-sha2-256` / `rsa-sha2-512` signatures.
+pub const SIGN_RSA_SHA2_256: u32 = 2;
+pub const SIGN_RSA_SHA2_512: u32 = 4;
+
+/// Longest message accepted, as in OpenSSH's agent.
+pub const MAX_MESSAGE_LEN: usize = 256 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentRequest {
+    RequestIdentities,
+    Sign { key_blob: Vec<u8>, data: Vec<u8>, flags: u32 },
+    /// Anything else, by message type.
+    Unsupported(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub key_blob: Vec<u8>,
+    pub comment: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentResponse {
+    Identities(Vec<Identity>),
+    Signature(Vec<u8>),
+    Failure,
+}
+
+impl AgentRequest {
+    /// Parse one message, without its length prefix.
+    pub fn decode(message: &[u8]) -> Result<Self> {
+        let (&kind, mut body) = message.split_first()
+            .ok_or_else(|| Error::InvalidInput("Empty SSH agent message".to_string()))?;
+        match kind {
+            REQUEST_IDENTITIES => Ok(AgentRequest::RequestIdentities),
+            SIGN_REQUEST => Ok(AgentRequest::Sign {
+                key_blob: read_string(&mut body)?.to_vec(),
+                data: read_string(&mut body)?.to_vec(),
+                flags: read_u32(&mut body)?,
+            }),
+            other => Ok(AgentRequest::Unsupported(other)),
+        }
+    }
+}
+
+impl AgentResponse {
+    /// The message with its length prefix, ready to write.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        match self {
+            AgentResponse::Identities(identities) => {
+                body.push(IDENTITIES_ANSWER);
+                body.extend_from_slice(&(identities.len() as u32).to_be_bytes());
+                for identity in identities {
+                    write_string(&mut body, &identity.key_blob);
+                    write_string(&mut body, identity.comment.as_bytes());
+                }
+            }
+            AgentResponse::Signature(signature) => {
+                body.push(SIGN_RESPONSE);
+                write_string(&mut body, signature);
+            }
+            AgentResponse::Failure => body.push(FAILURE),
+        }
+        let mut message = (body.len() as u32).to_be_bytes().to_vec();
+        message.extend(body);
+        message
+    }
+}
+
+fn read_u32(input: &mut &[u8]) -> Result<u32> {
+    let (bytes, rest) = input.split_first_chunk::<4>()
+        .ok_or_else(|| Error::InvalidInput("Truncated SSH agent message".to_string()))?;
+    *input = rest;
+    Ok(u32::from_be_bytes(*bytes))
+}
+
+fn read_string<'a>(input: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = read_u32(input)? as usize;
+    if input.len() < len {
+        return Err(Error::InvalidInput("Truncated SSH agent message".to_string()));
+    }
+    let (value, rest) = input.split_at(len);
+    *input = rest;
+    Ok(value)
+}
+
+fn write_string(out: &mut Vec<u8>, value: &[u8]) {
+    out.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    out.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_requests_decode_and_answers_encode() {
+        let mut sign = vec![SIGN_REQUEST];
+        write_string(&mut sign, b"blob");
+        write_string(&mut sign, b"data");
+        sign.extend_from_slice(&SIGN_RSA_SHA2_256.to_be_bytes());
+        assert_eq!(AgentRequest::decode(&sign).unwrap(), AgentRequest::Sign {
+            key_blob: b"blob".to_vec(),
+            data: b"data".to_vec(),
+            flags: SIGN_RSA_SHA2_256,
+        });
+        assert!(AgentRequest::decode(&sign[..7]).is_err());
+        assert_eq!(AgentRequest::decode(&[17]).unwrap(), AgentRequest::Unsupported(17));
+
+        let answer = AgentResponse::Identities(vec![Identity { key_blob: b"k".to_vec(), comment: "c".to_string() }]);
+        assert_eq!(answer.encode(), [0, 0, 0, 15, IDENTITIES_ANSWER, 0, 0, 0, 1, 0, 0, 0, 1, b'k', 0, 0, 0, 1, b'c']);
+        assert_eq!(AgentResponse::Failure.encode(), [0, 0, 0, 1, FAILURE]);
+    }
+}
@@ -0,0 +1,284 @@
+//! Secret references in config file templates, rendered by `powda inject`.
+//!
+//! Two spellings are understood:
+//!
+//! ```text
+//! DB_PASSWORD={{ powda "db/prod" "password" }}
+//! DB_USER=powda://db/prod/username
+//! ```
+//!
+//! The field is optional in both and defaults to the password. In the URI
+//! form the last segment is the field only if it is a field name, so
+//! `powda://db/prod` is the password of `db/prod`; characters that would
+//! end the URI (spaces, quotes, brackets) can be written as `%20` and so on.
+//! `{{ ... }}` blocks that do not start with `powda` are left alone for
+//! other template engines.
+
+use crate::domain::{EntryName, PasswordEntry};
+use crate::error::{Error, Result};
+
+const URI_SCHEME: &str = "powda://";
+
+/// The part of an entry a reference asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Password,
+    Username,
+    Url,
+    Notes,
+    Tags,
+}
+
+impl Field {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "password" => Some(Field::Password),
+            "username" => Some(Field::Username),
+            "url" => Some(Field::Url),
+            "notes" => Some(Field::Notes),
+            "tags" => Some(Field::Tags),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::Password => "password",
+            Field::Username => "username",
+            Field::Url => "url",
+            Field::Notes => "notes",
+            Field::Tags => "tags",
+        }
+    }
+
+    /// The value of this field in `entry`, if it has one.
+    pub fn value(&self, entry: &PasswordEntry) -> Option<String> {
+        let metadata = &entry.metadata;
+        match self {
+            Field::Password => Some(entry.password.as_str().to_string()),
+            Field::Username => metadata.username.clone(),
+            Field::Url => metadata.url.clone(),
+            Field::Notes => metadata.notes.clone(),
+            Field::Tags => (!metadata.tags.is_empty()).then(|| metadata.tags.join(",")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub name: EntryName,
+    pub field: Field,
+    /// 1-based line the reference starts on, for error messages.
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Reference(Reference),
+}
+
+/// A parsed template: literal text with references in between.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = text;
+
+        while let Some(start) = next_reference(rest) {
+            literal.push_str(&rest[..start]);
+            let line = text[..text.len() - rest.len() + start].matches('\n').count() + 1;
+            let at = &rest[start..];
+
+            let parsed = if at.starts_with(URI_SCHEME) {
+                parse_uri(at).map(Some)
+            } else {
+                parse_block(at)
+            };
+            match parsed.map_err(|e| Error::InvalidInput(format!("line {}: {}", line, e)))? {
+                Some((name, field, len)) => {
+                    if !literal.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut literal)));
+                    }
+                    let name = EntryName::new(name).map_err(|e| Error::InvalidInput(format!("line {}: {}", line, e)))?;
+                    parts.push(Part::Reference(Reference { name, field, line }));
+                    rest = &at[len..];
+                }
+                // Someone else's `{{ ... }}`; keep the braces and move on.
+                None => {
+                    literal.push_str("{{");
+                    rest = &at[2..];
+                }
+            }
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(Part::Text(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    pub fn references(&self) -> impl Iterator<Item = &Reference> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Reference(reference) => Some(reference),
+            Part::Text(_) => None,
+        })
+    }
+
+    /// Substitute every reference using `lookup`. All unresolved
+    /// references are reported together, each with its line.
+    pub fn render<'a>(&self, lookup: impl Fn(&EntryName) -> Option<&'a PasswordEntry>) -> Result<String> {
+        let mut out = String::new();
+        let mut unresolved = Vec::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Reference(reference) => match lookup(&reference.name) {
+                    None => unresolved.push(format!(
+                        "line {}: no entry named '{}'", reference.line, reference.name.as_str()
+                    )),
+                    Some(entry) => match reference.field.value(entry) {
+                        Some(value) => out.push_str(&value),
+                        None => unresolved.push(format!(
+                            "line {}: '{}' has no {}", reference.line, reference.name.as_str(), reference.field.name()
+                        )),
+                    },
+                },
+            }
+        }
+
+        if !unresolved.is_empty() {
+            return Err(Error::InvalidInput(format!("Unresolved references:\n  {}", unresolved.join("\n  "))));
+        }
+        Ok(out)
+    }
+}
+
+/// Offset of the next `{{` or `powda://` in `text`.
+fn next_reference(text: &str) -> Option<usize> {
+    [text.find("{{"), text.find(URI_SCHEME)].into_iter().flatten().min()
+}
+
+/// `powda://name[/field]`, returning the name, field and length used.
+fn parse_uri(text: &str) -> std::result::Result<(String, Field, usize), String> {
+    let path = &text[URI_SCHEME.len()..];
+    let end = path.find(|c: char| c.is_whitespace() || "\"'`<>()[]{},;".contains(c)).unwrap_or(path.len());
+    // A sentence may end right after a reference.
+    let raw = path[..end].trim_end_matches(['.', ':']);
+    let decoded = percent_decode(raw)?;
+
+    let (name, field) = match decoded.rsplit_once('/') {
+        Some((name, field)) if !name.is_empty() => match Field::parse(field) {
+            Some(field) => (name.to_string(), field),
+            None => (decoded.clone(), Field::Password),
+        },
+        _ => (decoded.clone(), Field::Password),
+    };
+    if name.is_empty() {
+        return Err(format!("'{}' does not name an entry", URI_SCHEME));
+    }
+    Ok((name, field, URI_SCHEME.len() + raw.len()))
+}
+
+/// `{{ powda "name" ["field"] }}`. `Ok(None)` means the block is not ours.
+fn parse_block(text: &str) -> std::result::Result<Option<(String, Field, usize)>, String> {
+    let inner = text[2..].trim_start();
+    let Some(args) = inner.strip_prefix("powda") else {
+        return Ok(None);
+    };
+    if !args.starts_with(char::is_whitespace) {
+        return Ok(None);
+    }
+
+    let (name, after) = quoted(args.trim_start())?;
+    let after = after.trim_start();
+    let (field, after) = if after.starts_with('"') {
+        let (field, after) = quoted(after)?;
+        let field = Field::parse(&field).ok_or_else(|| format!(
+            "unknown field '{}'; use password, username, url, notes or tags", field
+        ))?;
+        (field, after.trim_start())
+    } else {
+        (Field::Password, after)
+    };
+
+    let after = after.strip_prefix("}}").ok_or("expected '}}' to close '{{ powda'")?;
+    Ok(Some((name, field, text.len() - after.len())))
+}
+
+/// A double-quoted string with `\"` and `\\` escapes, and what follows it.
+fn quoted(text: &str) -> std::result::Result<(String, &str), String> {
+    let mut chars = text.strip_prefix('"').ok_or("expected a quoted entry name after 'powda'")?.char_indices();
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &text[i + 2..])),
+            '\\' => match chars.next() {
+                Some((_, escaped)) => value.push(escaped),
+                None => break,
+            },
+            '\n' => break,
+            c => value.push(c),
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+fn percent_decode(text: &str) -> std::result::Result<String, String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = tail.get(..2).and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| format!("bad percent escape in '{}'", text))?;
+            bytes.push(hex);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| format!("'{}' is not UTF-8 once decoded", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{EntryMetadata, Password};
+
+    #[test]
+    fn renders_both_spellings_and_reports_every_unresolved_reference() {
+        let db = PasswordEntry::new(EntryName::new("db/prod".to_string()).unwrap(), Password::new("s3cret".to_string()).unwrap())
+            .with_metadata(EntryMetadata { username: Some("app".to_string()), ..Default::default() });
+        let lookup = |name: &EntryName| (name == &db.name).then_some(&db);
+
+        let template = Template::parse(concat!(
+            "PASS={{ powda \"db/prod\" \"password\" }}\n",
+            "USER=powda://db/prod/username\n",
+            "DEFAULT={{powda \"db/prod\"}} url: powda://db%2Fprod.\n",
+            "helm: {{ .Values.image }}\n",
+        )).unwrap();
+        assert_eq!(template.references().count(), 4);
+        assert_eq!(template.render(lookup).unwrap(), concat!(
+            "PASS=s3cret\n",
+            "USER=app\n",
+            "DEFAULT=s3cret url: s3cret.\n",
+            "helm: {{ .Values.image }}\n",
+        ));
+
+        let template = Template::parse("a=powda://db/prod/url\nb={{ powda \"db/dev\" }}\n").unwrap();
+        let error = template.render(lookup).unwrap_err().to_string();
+        assert!(error.contains("line 1: 'db/prod' has no url"), "{}", error);
+        assert!(error.contains("line 2: no entry named 'db/dev'"), "{}", error);
+
+        assert!(Template::parse("x\n{{ powda \"db/prod\" \"secret\" }}").unwrap_err().to_string().contains("line 2"));
+        assert!(Template::parse("{{ powda \"db/prod\" ").is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Format a timestamp as RFC 3339 (`YYYY-MM-DDTHH:MM:SSZ`).
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = civil(time);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, minute, second)
+}
+
+/// Parse an RFC 3339 UTC timestamp such as `2024-01-31T12:34:56.789Z`.
+/// Fractional seconds are dropped; offsets other than `Z` are rejected.
+pub fn parse_rfc3339(value: &str) -> Option<SystemTime> {
+    let (date, time) = value.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let time = time.strip_suffix('Z')?.split('.').next()?;
+    let mut time = time.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+
+    // Days-from-civil, the inverse of `civil` below.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86_400 + hour * 3_600 + minute * 60 + second;
+    u64::try_from(secs).ok().map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Parse a `YYYY-MM-DD` date (midnight UTC) or an RFC 3339 timestamp.
+pub fn parse_date(value: &str) -> Option<SystemTime> {
+    match value.contains('T') {
+        true => parse_rfc3339(value),
+        false => parse_rfc3339(&format!("{}T00:00:00Z", value)),
+    }
+}
+
+/// Split a timestamp into UTC `(year, month, day, hour, minute, second)`.
+pub fn civil(time: SystemTime) -> (i64, i64, i64, u64, u64, u64) {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil-from-days, proleptic Gregorian calendar.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day, rem / 3_600, rem % 3_600 / 60, rem % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc3339_round_trips() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(format_rfc3339(time), "2024-02-29T12:34:56Z");
+        assert_eq!(parse_rfc3339("2024-02-29T12:34:56.789Z"), Some(time));
+        assert_eq!(parse_rfc3339("2024-02-29T12:34:56+01:00"), None);
+        assert_eq!(parse_date("2024-02-29"), Some(time - Duration::from_secs(45_296)));
+    }
+}
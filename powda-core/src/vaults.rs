@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::DirBuilder;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::repository::atomic_write;
+
+/// Vault used when nothing else is selected or configured.
+pub const DEFAULT_VAULT: &str = "default";
+/// Environment variable selecting a vault by name or path.
+pub const VAULT_ENV: &str = "POWDA_VAULT";
+
+/// `powda`'s settings, next to the registry in the config directory.
+pub const CONFIG_FILE: &str = "config.toml";
+
+const REGISTRY_FILE: &str = "vaults.toml";
+const LEGACY_VAULT_FILE: &str = ".powda_vault.encrypted";
+
+/// `$XDG_CONFIG_HOME/powda`, falling back to `~/.config/powda`.
+pub fn config_dir() -> Result<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// `$XDG_DATA_HOME/powda`, falling back to `~/.local/share/powda`.
+pub fn data_dir() -> Result<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> Result<PathBuf> {
+    // The spec says relative values are invalid and must be ignored.
+    if let Some(dir) = std::env::var_os(var).map(PathBuf::from).filter(|dir| dir.is_absolute()) {
+        return Ok(dir.join("powda"));
+    }
+    Ok(home()?.join(fallback).join("powda"))
+}
+
+fn home() -> Result<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| Error::InvalidInput("HOME is not set, cannot locate the vault".to_string()))
+}
+
+/// Create `dir` and its parents with 0700, leaving existing ones alone.
+pub fn create_private_dir(dir: &Path) -> Result<()> {
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    Ok(())
+}
+
+/// A vault file, and the name it is registered under if it has one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultLocation {
+    pub name: Option<String>,
+    pub path: PathBuf,
+}
+
+impl VaultLocation {
+    /// Registered name, or the path for vaults selected by path.
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self.path.display().to_string(),
+        }
+    }
+}
+
+/// Named vaults, stored in `vaults.toml` under the config directory:
+///
+/// ```toml
+/// default = "personal"
+///
+/// [vaults]
+/// personal = "/home/me/.local/share/powda/personal.vault"
+/// ops = "/srv/team/ops.vault"
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VaultRegistry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<String>,
+    #[serde(default)]
+    vaults: BTreeMap<String, PathBuf>,
+    #[serde(skip)]
+    file: PathBuf,
+}
+
+impl VaultRegistry {
+    pub fn load() -> Result<Self> {
+        Self::load_from(config_dir()?.join(REGISTRY_FILE))
+    }
+
+    /// Read the registry at `file`; a missing file is an empty registry.
+    pub fn load_from(file: PathBuf) -> Result<Self> {
+        let mut registry: Self = match std::fs::read_to_string(&file) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|e| Error::InvalidInput(format!("{}: {}", file.display(), e.message())))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        registry.file = file;
+        Ok(registry)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.file.parent() {
+            create_private_dir(dir)?;
+        }
+        let text = toml::to_string(self).map_err(|e| Error::InvalidInput(e.to_string()))?;
+        atomic_write(&self.file, text.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn default_name(&self) -> &str {
+        self.default.as_deref().unwrap_or(DEFAULT_VAULT)
+    }
+
+    /// Pick the vault to use: `selector` (from `--vault`), else
+    /// `$POWDA_VAULT`, else the configured default. Selectors containing
+    /// a `/` are paths, anything else is a registered name.
+    pub fn resolve(&self, selector: Option<&str>) -> Result<VaultLocation> {
+        let from_env = std::env::var(VAULT_ENV).ok().filter(|v| !v.is_empty());
+        let selector = selector.map(str::to_string).or(from_env);
+
+        match selector {
+            Some(path) if path.contains('/') => Ok(VaultLocation { name: None, path: PathBuf::from(path) }),
+            Some(name) => self.get(&name),
+            None => self.get(self.default_name()),
+        }
+    }
+
+    /// The vault `powda` uses when no `--vault` is given: `$POWDA_VAULT`,
+    /// else the `vault` key of `config.toml`, else the default. For the
+    /// helper binaries, which take no flags and load no other settings.
+    pub fn resolve_configured(&self) -> Result<VaultLocation> {
+        if std::env::var(VAULT_ENV).is_ok_and(|v| !v.is_empty()) {
+            return self.resolve(None);
+        }
+        let config = self.file.with_file_name(CONFIG_FILE);
+        self.resolve(configured_vault(&config)?.as_deref())
+    }
+
+    /// Location of the vault registered as `name`. The default vault
+    /// works without registering: it lives in the data directory, or in
+    /// the old `~/.powda_vault.encrypted` if that is still around.
+    pub fn get(&self, name: &str) -> Result<VaultLocation> {
+        let path = match self.vaults.get(name) {
+            Some(path) => path.clone(),
+            None if name == DEFAULT_VAULT => implicit_default()?,
+            None => return Err(Error::InvalidInput(format!(
+                "no vault named '{}'; see 'powda vault list'", name
+            ))),
+        };
+        Ok(VaultLocation { name: Some(name.to_string()), path })
+    }
+
+    /// Every known vault, including the implicit default one.
+    pub fn list(&self) -> Result<Vec<VaultLocation>> {
+        let mut vaults: Vec<VaultLocation> = self.vaults.iter()
+            .map(|(name, path)| VaultLocation { name: Some(name.clone()), path: path.clone() })
+            .collect();
+        if !self.vaults.contains_key(DEFAULT_VAULT) {
+            vaults.insert(0, self.get(DEFAULT_VAULT)?);
+        }
+        Ok(vaults)
+    }
+
+    /// Register `name` at `path`, or at `<data dir>/<name>.vault`.
+    pub fn add(&mut self, name: &str, path: Option<PathBuf>) -> Result<VaultLocation> {
+        validate_name(name)?;
+        if self.vaults.contains_key(name) || (name == DEFAULT_VAULT && implicit_default()?.exists()) {
+            return Err(Error::InvalidInput(format!("a vault named '{}' already exists", name)));
+        }
+
+        let path = match path {
+            Some(path) if path.is_absolute() => path,
+            Some(path) => std::env::current_dir()?.join(path),
+            None => data_dir()?.join(format!("{}.vault", name)),
+        };
+        self.vaults.insert(name.to_string(), path.clone());
+        Ok(VaultLocation { name: Some(name.to_string()), path })
+    }
+
+    /// Forget `name`; the vault file itself is left in place.
+    pub fn remove(&mut self, name: &str) -> Result<VaultLocation> {
+        let path = self.vaults.remove(name).ok_or_else(|| Error::InvalidInput(format!(
+            "no vault named '{}' is registered", name
+        )))?;
+        if self.default.as_deref() == Some(name) {
+            self.default = None;
+        }
+        Ok(VaultLocation { name: Some(name.to_string()), path })
+    }
+
+    pub fn set_default(&mut self, name: &str) -> Result<()> {
+        self.get(name)?;
+        self.default = (name != DEFAULT_VAULT).then(|| name.to_string());
+        Ok(())
+    }
+}
+
+/// The `vault` key of the config file at `path`, if it is set.
+fn configured_vault(path: &Path) -> Result<Option<String>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let config: toml::Table = toml::from_str(&text)
+        .map_err(|e| Error::InvalidInput(format!("{}: {}", path.display(), e.message())))?;
+    match config.get("vault") {
+        None => Ok(None),
+        Some(toml::Value::String(vault)) => Ok(Some(vault.clone())),
+        Some(_) => Err(Error::InvalidInput(format!("{}: 'vault' must be a string", path.display()))),
+    }
+}
+
+fn implicit_default() -> Result<PathBuf> {
+    let legacy = home()?.join(LEGACY_VAULT_FILE);
+    let current = data_dir()?.join(format!("{}.vault", DEFAULT_VAULT));
+    Ok(if legacy.exists() && !current.exists() { legacy } else { current })
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && !name.starts_with(['.', '-'])
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(Error::InvalidInput(format!(
+            "'{}' is not a valid vault name (letters, digits, '-', '_' and '.')", name
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_round_trips_and_resolves_names_and_paths() {
+        let dir = std::env::temp_dir().join(format!("powda-vaults-{}", std::process::id()));
+        let file = dir.join(REGISTRY_FILE);
+
+        let mut registry = VaultRegistry::load_from(file.clone()).unwrap();
+        registry.add("ops", Some(dir.join("ops.vault"))).unwrap();
+        registry.set_default("ops").unwrap();
+        assert!(registry.add("ops", None).is_err());
+        assert!(registry.add("../escape", None).is_err());
+        registry.save().unwrap();
+
+        let registry = VaultRegistry::load_from(file).unwrap();
+        assert_eq!(registry.default_name(), "ops");
+        assert_eq!(registry.resolve(Some("ops")).unwrap().path, dir.join("ops.vault"));
+        assert_eq!(registry.resolve(Some("./other.vault")).unwrap().name, None);
+        assert!(registry.resolve(Some("missing")).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn helpers_pick_the_vault_named_in_the_config_file() {
+        if std::env::var_os(VAULT_ENV).is_some() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("powda-vaults-config-{}", std::process::id()));
+        let mut registry = VaultRegistry::load_from(dir.join(REGISTRY_FILE)).unwrap();
+        registry.add("ops", Some(dir.join("ops.vault"))).unwrap();
+        registry.add("work", Some(dir.join("work.vault"))).unwrap();
+        registry.set_default("work").unwrap();
+        assert_eq!(registry.resolve_configured().unwrap().name.as_deref(), Some("work"));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(CONFIG_FILE), "vault = \"ops\"\n[clipboard]\nclear_after = 10\n").unwrap();
+        assert_eq!(registry.resolve_configured().unwrap().path, dir.join("ops.vault"));
+
+        std::fs::write(dir.join(CONFIG_FILE), "vault = 3\n").unwrap();
+        assert!(registry.resolve_configured().is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}